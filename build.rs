@@ -4,16 +4,17 @@ use std::fs;
 
 fn main() {
     println!("cargo::rerun-if-changed=src/");
-    // Find the linecount of all .rs files to display on our webpage
+    println!("cargo::rerun-if-changed=glyph-core/src/");
+    // Find the linecount of all .rs files in the workspace, displayed on our webpage and logged on desktop
     let out_dir = env::var("OUT_DIR").unwrap();
     let linecount_path = Path::new(&out_dir).join("linecount.txt");
         
     let cargo_manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     let src_path = Path::new(&cargo_manifest_dir).join("src/");
+    let glyph_core_path = Path::new(&cargo_manifest_dir).join("glyph-core/src/");
         
-    let linecount = parse_dir(&src_path);
-    let linecount_string = "\"".to_string() + &linecount.to_string() + "\"";
-    fs::write(&linecount_path, linecount_string).unwrap();
+    let linecount = parse_dir(&src_path) + parse_dir(&glyph_core_path);
+    fs::write(&linecount_path, linecount.to_string()).unwrap();
 }
 
 // Check all items in the directory, recurse on a directory, call count_lines on a .rs file
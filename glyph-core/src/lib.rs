@@ -0,0 +1,8 @@
+// The cpu side model and pixel math used by wasm-wgpu, split out from the wgpu upload code.
+// Build with --no-default-features to use it without std (an allocator is still required)
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod model;
+pub mod pixel;
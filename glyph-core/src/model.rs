@@ -0,0 +1,601 @@
+// model.rs
+//
+// The Model builders used to create the letters. These are plain geometry operations on Vecs of
+// verts and indices, they don't need the OS or a gpu.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vert {
+    position: [f32; 3],
+    tex_coords: [f32; 2],
+    // Used for the lighting, the flat models all face +z
+    normal: [f32; 3],
+}
+
+impl Vert {
+    // For geometry made elsewhere, e.g. loaded from a file
+    pub fn new(position: [f32; 3], tex_coords: [f32; 2], normal: [f32; 3]) -> Self {
+        Vert {
+            position,
+            tex_coords,
+            normal,
+        }
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    pub fn tex_coords(&self) -> [f32; 2] {
+        self.tex_coords
+    }
+
+    pub fn normal(&self) -> [f32; 3] {
+        self.normal
+    }
+
+    fn new_white(position: [f32; 3]) -> Self {
+        Vert {
+            position,
+            tex_coords: [position[0], position[1]],
+            normal: [0.0, 0.0, 1.0],
+        }
+    }
+}
+
+// How reset_tex_coords treats verts outside of x=[-0.5,0.5] and y=[0,1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TexCoordWrap {
+    Clamp,
+    // Left outside of [0,1], for the sampler's address mode to wrap or mirror
+    Unclamped,
+}
+
+// Axis aligned bounding box of a model's vert positions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+// A model has more verts than its u16 indices can address
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IndexOverflow {
+    pub vert_count: usize,
+}
+
+impl core::fmt::Display for IndexOverflow {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "Model has {} verts but u16 indices can only address {}, the index buffers would need to switch to u32",
+            self.vert_count,
+            u16::MAX as usize + 1,
+        )
+    }
+}
+
+impl core::error::Error for IndexOverflow {}
+
+#[derive(Clone, Debug)]
+pub struct Model {
+    pub verts: Vec<Vert>,
+    pub tri_idxs: Vec<[u16; 3]>,
+}
+
+impl Model {
+
+    pub fn number_indices(&self) -> u32 {
+        self.tri_idxs.len() as u32 * 3
+    }
+
+    // Returns None for models without any verts (the unimplemented letters)
+    pub fn bounds(&self) -> Option<Bounds> {
+        let first = self.verts.first()?.position;
+        let mut bounds = Bounds { min: first, max: first };
+        for vert in &self.verts {
+            for i in 0..3 {
+                bounds.min[i] = f32::min(bounds.min[i], vert.position[i]);
+                bounds.max[i] = f32::max(bounds.max[i], vert.position[i]);
+            }
+        }
+        Some(bounds)
+    }
+
+    // Takes in verts and indices, except the verts are only the x and y
+    pub fn new_2d(vs: &[(f32, f32)], ts: &[[u16; 3]]) -> Self {
+        let mut verts: Vec<Vert> = vec![];
+        for &(x, y) in vs {
+            verts.push(Vert::new_white([x, y, 0.0]));
+        }
+        let mut tri_idxs: Vec<[u16; 3]> = vec![];
+        for &t in ts {
+            tri_idxs.push(t);
+        }
+        Model {
+            verts,
+            tri_idxs,
+        }
+    }
+
+    // Supply the verts in counter-clockwise order so the tri points the right way
+    pub fn tri_2d(vs: [(f32, f32); 3]) -> Self {
+        Self::new_2d(&vs, &[[0, 1, 2]])
+    }
+
+    // Create a Model (and it's indexed tris) from a 2d tristrip
+    // The first 3 verts must form a counter-clockwise tri, then the rest of the verts will follow
+    // in a zig-zag fashion
+    pub fn tristrip_2d(vs: &[(f32, f32)]) -> Self {
+        let mut indices: Vec<[u16; 3]> = vec![];
+        let mut flip = false;
+        // Every other tri must be flipped for the tristrip to be the right direction
+        for i in 0u16..(vs.len()-2) as u16 {
+            if flip {
+                indices.push([i, i+2, i+1]);
+            } else {
+                indices.push([i, i+1, i+2])
+            }
+            flip = !flip;
+        }
+        Self::new_2d(vs, indices.as_slice())
+    }
+
+    // A tristrip from the start edge to the end edge, split into pieces quads along the way so the
+    // displacement effect can bend it. Wound counter-clockwise whichever way the edges are given
+    pub fn strip_2d(start: [(f32, f32); 2], end: [(f32, f32); 2], pieces: u32) -> Self {
+        let lerp = |a: (f32, f32), b: (f32, f32), t: f32| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+        let mut vs = vec![];
+        for i in 0..=pieces {
+            let t = i as f32 / pieces as f32;
+            vs.push(lerp(start[0], end[0], t));
+            vs.push(lerp(start[1], end[1], t));
+        }
+        let strip = Self::tristrip_2d(&vs);
+        let [a, b, c] = [vs[0], vs[1], vs[2]];
+        if (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0) < 0.0 {
+            strip.flip()
+        } else {
+            strip
+        }
+    }
+
+    pub fn append_tri_2d(self, vs: [(f32, f32); 3]) -> Self {
+        self.append(Self::tri_2d(vs))
+    }
+
+    // Supply the verts in counter-clockwise order so the tris point the right way
+    pub fn rect_2d(vs: [(f32, f32); 4]) -> Self {
+        Self::new_2d(
+            &vs,
+            &[
+                [0, 1, 3],
+                [1, 2, 3],
+            ],
+        )
+    }
+
+    // A flat bar spanning x=[left, right], y=[bottom, top] at z. Every vert samples the texture at
+    // the same height tex_y, so the bar takes the color letters have at that height
+    pub fn bar(left: f32, right: f32, bottom: f32, top: f32, z: f32, tex_y: f32) -> Self {
+        let mut bar = Self::rect_2d([
+            (right, bottom),
+            (right, top),
+            (left, top),
+            (left, bottom),
+        ]);
+        for vert in &mut bar.verts {
+            vert.position[2] = z;
+            vert.tex_coords = [0.5, tex_y];
+        }
+        bar
+    }
+
+    // Like rect_2d, but split into a subdivisions x subdivisions grid of quads so the displacement effect
+    // can bend it instead of moving it as one rigid shape. The grid is interpolated between the 4 corners
+    pub fn subdivided_rect(subdivisions: u32, vs: [(f32, f32); 4]) -> Self {
+        let n = subdivisions.max(1);
+        let lerp = |a: (f32, f32), b: (f32, f32), t: f32| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+        // Row i runs from the vs[0]-vs[1] edge to the vs[3]-vs[2] edge
+        let mut verts = vec![];
+        for i in 0..=n {
+            let t = i as f32 / n as f32;
+            let (start, end) = (lerp(vs[0], vs[1], t), lerp(vs[3], vs[2], t));
+            for j in 0..=n {
+                verts.push(lerp(start, end, j as f32 / n as f32));
+            }
+        }
+        // Each cell is wound the same way as rect_2d
+        let index = |i: u32, j: u32| (i * (n + 1) + j) as u16;
+        let mut tris = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                tris.push([index(i, j), index(i + 1, j), index(i, j + 1)]);
+                tris.push([index(i + 1, j), index(i + 1, j + 1), index(i, j + 1)]);
+            }
+        }
+        Self::new_2d(&verts, &tris)
+    }
+
+    pub fn append_rect_2d(self, vs: [(f32, f32); 4]) -> Self {
+        self.append(Self::rect_2d(vs))
+    }
+
+    // Appends self with f applied to a copy of self
+    pub fn append_apply(self, f: impl FnOnce(Model) -> Model) -> Self{
+        let clone = self.clone();
+        self.append(f(clone))
+    }
+
+    // Apply must change the indices appropriately to work with the right verts. Shared verts aren't combined,
+    // see optimizing_pass
+    pub fn append(self, m: Model) -> Self {
+        match self.try_append(m) {
+            Ok(model) => model,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    // Fails instead of wrapping the u16 indices when the combined model has too many verts
+    pub fn try_append(mut self, mut m: Model) -> Result<Self, IndexOverflow> {
+        let vert_count = self.verts.len() + m.verts.len();
+        if vert_count > u16::MAX as usize + 1 {
+            return Err(IndexOverflow { vert_count });
+        }
+        //Correct m's indices by adding the len of self.verts
+        for tri_idx in &mut m.tri_idxs {
+            for idx in tri_idx {
+                *idx += self.verts.len() as u16;
+            }
+        }
+        self.tri_idxs.append(&mut m.tri_idxs);
+        self.verts.append(&mut m.verts);
+        Ok(self)
+    }
+    
+    //Flips the triangle so it's pointing in the opposite direction
+    pub fn flip(mut self) -> Self {
+        for idx in &mut self.tri_idxs {
+            *idx = [
+                idx[0],
+                idx[2],
+                idx[1],
+            ];
+        }
+        self
+    }
+
+    pub fn mult(self, x: f32, y: f32, z: f32) -> Model {
+        let mut m = self.vert_mod( |arr| [x * arr[0], y * arr[1], z * arr[2]] );
+        // Normals scale by the inverse, so they stay perpendicular to the stretched surface
+        for vert in &mut m.verts {
+            let n = [vert.normal[0] / x, vert.normal[1] / y, vert.normal[2] / z];
+            vert.normal = normalize(n).unwrap_or(vert.normal);
+        }
+        m
+    }
+
+    // uses function f on all the vert positions. The normals are kept, which is only right for flat models
+    // moved within their plane
+    pub fn vert_mod<F>(mut self, f: F) -> Self 
+    where F: Fn([f32;3]) -> [f32;3] {
+        for vert in &mut self.verts {
+            vert.position = f(vert.position);
+        }
+        self
+    }
+
+    // Resets the texture coordinates to = the x+0.5 and y vertex positions, clamped to [0,1] so
+    // geometry outside of x=[-0.5,0.5] and y=[0,1] samples the texture's edge instead of mirrored texels
+    pub fn reset_tex_coords(self) -> Self {
+        self.reset_tex_coords_with(TexCoordWrap::Clamp)
+    }
+
+    pub fn reset_tex_coords_with(mut self, wrap: TexCoordWrap) -> Self {
+        for vert in &mut self.verts {
+            let tex_coords = [vert.position[0] + 0.5, vert.position[1]];
+            vert.tex_coords = match wrap {
+                TexCoordWrap::Clamp => tex_coords.map(|c| c.clamp(0.0, 1.0)),
+                TexCoordWrap::Unclamped => tex_coords,
+            };
+        }
+        self
+    }
+
+    // Rasterizes the model's triangles, viewed from the front, into a width * height coverage map.
+    // The image spans x=[-0.5,0.5] and y=[0,1] with row 0 at the top. Each pixel takes 4x4 samples
+    pub fn rasterize(&self, width: u32, height: u32) -> Vec<u8> {
+        const SAMPLES: u32 = 4;
+        let mut coverage = vec![0u8; (width * height) as usize];
+        let edge = |a: [f32; 3], b: [f32; 3], p: [f32; 2]| (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0]);
+        for y in 0..height {
+            for x in 0..width {
+                let mut hits = 0;
+                for sy in 0..SAMPLES {
+                    for sx in 0..SAMPLES {
+                        let p = [
+                            (x as f32 + (sx as f32 + 0.5) / SAMPLES as f32) / width as f32 - 0.5,
+                            1.0 - (y as f32 + (sy as f32 + 0.5) / SAMPLES as f32) / height as f32,
+                        ];
+                        // Inside when the sample is on the same side of all 3 edges, whichever the winding
+                        let inside = self.tri_idxs.iter().any(|tri| {
+                            let [a, b, c] = tri.map(|i| self.verts[i as usize].position);
+                            let e = [edge(a, b, p), edge(b, c, p), edge(c, a, p)];
+                            e.iter().all(|&e| e >= 0.0) || e.iter().all(|&e| e <= 0.0)
+                        });
+                        hits += inside as u32;
+                    }
+                }
+                coverage[(y * width + x) as usize] = (hits * 255 / (SAMPLES * SAMPLES)) as u8;
+            }
+        }
+        coverage
+    }
+
+    // Deduplicates vertices: verts with positions, tex coords and normals equal within 1e-5 are merged into one,
+    // and verts no tri uses are dropped. Comparing the difference keeps 0.0 and -0.0 equal. The tris are unchanged
+    pub fn optimizing_pass(self) -> Model {
+        const EPSILON: f32 = 1e-5;
+        let close = |a: &Vert, b: &Vert| {
+            a.position.iter().chain(&a.tex_coords).chain(&a.normal)
+                .zip(b.position.iter().chain(&b.tex_coords).chain(&b.normal))
+                .all(|(a, b)| (a - b).abs() <= EPSILON)
+        };
+        let mut verts: Vec<Vert> = vec![];
+        // Where each of the old verts ended up, filled in as the tris use them
+        let mut remap: Vec<Option<u16>> = vec![None; self.verts.len()];
+        let mut tri_idxs = self.tri_idxs;
+        for idx in tri_idxs.iter_mut().flatten() {
+            let old = *idx as usize;
+            *idx = *remap[old].get_or_insert_with(|| {
+                match verts.iter().position(|v| close(v, &self.verts[old])) {
+                    Some(existing) => existing as u16,
+                    None => {
+                        verts.push(self.verts[old]);
+                        verts.len() as u16 - 1
+                    }
+                }
+            });
+        }
+        Model {
+            verts,
+            tri_idxs,
+        }
+    }
+
+    // Turns the flat model into a solid depth thick. The front stays at z=0, a flipped copy becomes the
+    // back at z=-depth, and walls join them along the exterior edges. An edge is exterior when no other
+    // tri has an edge between the same 2 positions, so pieces that were appended with their own copies
+    // of the shared verts are still joined without a wall between them
+    pub fn extrude(self, depth: f32) -> Model {
+        let position = |i: u16| self.verts[i as usize].position;
+        let edges: Vec<(usize, [u16; 2])> = self.tri_idxs.iter().enumerate()
+            .flat_map(|(t, &[a, b, c])| [(t, [a, b]), (t, [b, c]), (t, [c, a])])
+            .collect();
+        let exterior = edges.iter().filter(|(t, [a, b])| {
+            !edges.iter().any(|(other_t, [c, d])| {
+                other_t != t && (
+                    (position(*a) == position(*c) && position(*b) == position(*d)) ||
+                    (position(*a) == position(*d) && position(*b) == position(*c))
+                )
+            })
+        });
+
+        // The front is wound counter-clockwise, so the inside of the model is to the left of each edge.
+        // Each wall is wound counter-clockwise viewed from outside
+        let mut walls = Model { verts: vec![], tri_idxs: vec![] };
+        for (_, [a, b]) in exterior {
+            let (a, b) = (self.verts[*a as usize], self.verts[*b as usize]);
+            // The outside is to the right of the edge
+            let [dx, dy, _] = sub(b.position, a.position);
+            let normal = normalize([dy, -dx, 0.0]).unwrap_or([0.0, 0.0, 1.0]);
+            let front = |v: Vert| Vert { normal, ..v };
+            let back = |v: Vert| Vert { position: [v.position[0], v.position[1], -depth], normal, ..v };
+            let i = walls.verts.len() as u16;
+            walls.verts.extend([front(a), front(b), back(b), back(a)]);
+            walls.tri_idxs.extend([[i, i + 2, i + 1], [i, i + 3, i + 2]]);
+        }
+
+        let mut back = self.clone().flip().vert_mod(|arr| [arr[0], arr[1], -depth]);
+        for vert in &mut back.verts {
+            vert.normal = [0.0, 0.0, -1.0];
+        }
+        self.append(back).append(walls)
+    }
+
+    // Recomputes every vert's normal from the tris that use it. Each tri's normal is weighted by its area,
+    // so slivers barely bend the result. Flat models come out +z, extruded ones get outward walls as long
+    // as the walls have their own verts (extrude gives them their own)
+    pub fn compute_normals(mut self) -> Self {
+        let mut sums = vec![[0.0f32; 3]; self.verts.len()];
+        for tri in &self.tri_idxs {
+            let [a, b, c] = tri.map(|i| self.verts[i as usize].position);
+            let (u, v) = (sub(b, a), sub(c, a));
+            // The cross product's length is twice the tri's area
+            let face = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            for &i in tri {
+                for k in 0..3 {
+                    sums[i as usize][k] += face[k];
+                }
+            }
+        }
+        for (vert, sum) in self.verts.iter_mut().zip(sums) {
+            // Verts without any tris keep the normal they had
+            if let Some(normal) = normalize(sum) {
+                vert.normal = normal;
+            }
+        }
+        self
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+// None for a zero length vector
+fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let length = sqrt(v[0] * v[0] + v[1] * v[1] + v[2] * v[2]);
+    (length > 0.0).then(|| v.map(|c| c / length))
+}
+
+// f32::sqrt needs std. Newton's method, starting from halving the exponent bits, converges in a few steps
+fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let mut guess = f32::from_bits((x.to_bits() >> 1) + 0x1fbd_1df5);
+    for _ in 0..4 {
+        guess = 0.5 * (guess + x / guess);
+    }
+    guess
+}
+
+pub fn mirror_x(m: Model) -> Model {
+    m.flip().mult(-1.0, 1.0, 1.0)
+}
+    
+pub fn mirror_y(m: Model) -> Model {
+    m.flip().vert_mod(|arr| [arr[0], -(arr[1] - 0.5) + 0.5, arr[2]])
+}
+
+// mirror over '/'
+pub fn mirror_forward_slash(m: Model) -> Model {
+    m.flip().vert_mod(|arr| [arr[1] - 0.5, arr[0] + 0.5, arr[2]])
+}
+// mirror over '\'
+pub fn mirror_back_slash(m: Model) -> Model {
+    m.flip().vert_mod(|arr| [0.5 - arr[1], 0.5 - arr[0], arr[2]])
+}
+//
+//fn mirror_z(self) -> Self {
+//    self.flip().mult(1.0, 1.0, -1.0)
+//}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // n verts along the x axis, with no tris
+    fn points(n: usize) -> Model {
+        let vs: Vec<(f32, f32)> = (0..n).map(|i| (i as f32, 0.0)).collect();
+        Model::new_2d(&vs, &[])
+    }
+
+    #[test]
+    fn appending_past_u16_indices_is_detected() {
+        let full = points(u16::MAX as usize + 1);
+        assert!(full.clone().try_append(points(0)).is_ok());
+        let error = full.try_append(Model::tri_2d([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)])).unwrap_err();
+        assert_eq!(error, IndexOverflow { vert_count: 65539 });
+    }
+
+    #[test]
+    #[should_panic(expected = "65539 verts")]
+    fn append_panics_naming_the_vert_count() {
+        points(u16::MAX as usize + 1).append(Model::tri_2d([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]));
+    }
+
+    #[test]
+    fn sqrt_matches_std() {
+        for x in [1e-6f32, 0.01, 0.5, 1.0, 2.0, 3.0, 10.0, 12345.678, 1e8] {
+            let expected = x.sqrt();
+            assert!((sqrt(x) - expected).abs() <= 1e-5 * expected, "sqrt({x}) = {} not {expected}", sqrt(x));
+        }
+        assert_eq!(sqrt(0.0), 0.0);
+        assert_eq!(sqrt(-4.0), 0.0);
+    }
+
+    #[test]
+    fn normalize_gives_unit_length() {
+        let n = normalize([3.0, 0.0, 4.0]).unwrap();
+        assert!((n[0] - 0.6).abs() < 1e-6 && (n[2] - 0.8).abs() < 1e-6);
+        assert!(normalize([0.0; 3]).is_none());
+    }
+
+    #[test]
+    fn tex_coords_past_the_edge_are_clamped() {
+        let tri = Model::tri_2d([(0.6, 0.5), (0.0, -0.2), (0.0, 1.0)]);
+        assert_eq!(tri.clone().reset_tex_coords().verts[0].tex_coords, [1.0, 0.5]);
+        assert_eq!(tri.clone().reset_tex_coords().verts[1].tex_coords, [0.5, 0.0]);
+        let unclamped = tri.reset_tex_coords_with(TexCoordWrap::Unclamped);
+        assert!((unclamped.verts[0].tex_coords[0] - 1.1).abs() < 1e-6);
+        assert_eq!(unclamped.verts[1].tex_coords[1], -0.2);
+    }
+
+    #[test]
+    fn extruded_quad_has_front_back_and_4_walls() {
+        let quad = Model::rect_2d([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        let solid = quad.extrude(0.25);
+        // 4 front verts, 4 back verts and 4 for each of the 4 walls
+        assert_eq!(solid.verts.len(), 4 + 4 + 16);
+        assert_eq!(solid.tri_idxs.len(), 2 + 2 + 8);
+        assert!(solid.verts[..4].iter().all(|v| v.position[2] == 0.0));
+        assert!(solid.verts[4..8].iter().all(|v| v.position[2] == -0.25));
+    }
+
+    #[test]
+    fn optimizing_pass_merges_duplicate_verts() {
+        // Two tris sharing an edge through their own copies of its verts, one with -0.0
+        let model = Model::new_2d(
+            &[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 1.0), (-0.0, 1.0), (5.0, 5.0)],
+            &[[0, 1, 2], [3, 4, 5]],
+        );
+        let optimized = model.clone().optimizing_pass();
+        // The duplicates and the unused vert are gone
+        assert_eq!(optimized.verts.len(), 4);
+        assert_eq!(optimized.tri_idxs.len(), model.tri_idxs.len());
+        for (old, new) in model.tri_idxs.iter().zip(&optimized.tri_idxs) {
+            for (&a, &b) in old.iter().zip(new) {
+                let (a, b) = (model.verts[a as usize].position, optimized.verts[b as usize].position);
+                assert!(a.iter().zip(&b).all(|(a, b)| a == b));
+            }
+        }
+
+        // Verts at the same place facing different ways, like the edges of an extruded letter, are kept apart
+        let mut creased = Model::tri_2d([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]).append(Model::tri_2d([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]));
+        for vert in &mut creased.verts[3..] {
+            vert.normal = [1.0, 0.0, 0.0];
+        }
+        assert_eq!(creased.optimizing_pass().verts.len(), 6);
+    }
+
+    #[test]
+    fn subdivided_rect_has_2_n_squared_ccw_tris() {
+        let corners = [(0.0, 0.0), (0.0, 2.0), (1.0, 2.0), (1.0, 0.0)];
+        for n in 1..=4 {
+            let grid = Model::subdivided_rect(n, corners);
+            assert_eq!(grid.tri_idxs.len() as u32, 2 * n * n);
+            assert_eq!(grid.verts.len() as u32, (n + 1) * (n + 1));
+        }
+        // Wound like rect_2d, counter-clockwise
+        let grid = Model::subdivided_rect(3, [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        let ccw = |model: &Model| model.tri_idxs.iter().all(|tri| {
+            let [a, b, c] = tri.map(|i| model.verts[i as usize].position);
+            (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]) > 0.0
+        });
+        assert!(ccw(&grid));
+    }
+
+    #[test]
+    fn extruded_cube_normals_point_outward() {
+        let cube = Model::rect_2d([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]).extrude(1.0).compute_normals();
+        let center = [0.5, 0.5, -0.5];
+        for vert in &cube.verts {
+            let normal = vert.normal();
+            let length = normal.iter().map(|n| n * n).sum::<f32>();
+            assert!((length - 1.0).abs() < 1e-5);
+            // Every normal is one of the 6 axis directions, on the side of the cube the vert is on
+            assert_eq!(normal.iter().filter(|n| n.abs() > 0.999).count(), 1, "{normal:?}");
+            let outward: f32 = (0..3).map(|k| normal[k] * (vert.position()[k] - center[k])).sum();
+            assert!((outward - 0.5).abs() < 1e-5, "{:?} has normal {normal:?}", vert.position());
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// pixel.rs
+//
+// Per-pixel operations for the RGBA textures
+
+// Pixel formats that can have their color channels multiplied by their alpha channel
+pub trait Premultiply: Copy {
+    fn premultiplied(self) -> Self;
+}
+
+impl Premultiply for [u8; 4] {
+    fn premultiplied(self) -> Self {
+        let mul = |c: u8| ((c as u16 * self[3] as u16 + 127) / 255) as u8;
+        [mul(self[0]), mul(self[1]), mul(self[2]), self[3]]
+    }
+}
+
+impl Premultiply for [f32; 4] {
+    fn premultiplied(self) -> Self {
+        [self[0] * self[3], self[1] * self[3], self[2] * self[3], self[3]]
+    }
+}
+
+// Pixel formats that can be averaged, for the box filter that builds each mip level from the one above it
+pub trait Downsample: Copy {
+    fn average(pixels: [Self; 4]) -> Self;
+}
+
+impl Downsample for [u8; 4] {
+    fn average(pixels: [Self; 4]) -> Self {
+        core::array::from_fn(|c| ((pixels.iter().map(|p| p[c] as u16).sum::<u16>() + 2) / 4) as u8)
+    }
+}
+
+impl Downsample for [f32; 4] {
+    fn average(pixels: [Self; 4]) -> Self {
+        core::array::from_fn(|c| pixels.iter().map(|p| p[c]).sum::<f32>() / 4.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn premultiplies_half_alpha_white() {
+        assert_eq!([255u8, 255, 255, 128].premultiplied(), [128, 128, 128, 128]);
+        assert_eq!([1.0f32, 1.0, 1.0, 0.5].premultiplied(), [0.5, 0.5, 0.5, 0.5]);
+    }
+}
@@ -0,0 +1,161 @@
+// displace.rs
+//
+// Displacement in a compute pass. Instead of every vertex working out its own offset from the focus,
+// a compute shader moves each letter instance as a whole, writing the moved instances into buffers
+// the letters are then drawn from. Needs compute shaders and storage buffers, which WebGL2 doesn't have.
+
+// Must match @workgroup_size in displace.wgsl
+const WORKGROUP_SIZE: u32 = 64;
+
+// Whether the device can run the compute pass: the shader reads the instances from one storage buffer and
+// writes them into another. The downlevel WebGL2 limits have no storage buffers or compute workgroups
+pub fn supported(device: &wgpu::Device) -> bool {
+    let limits = device.limits();
+    limits.max_storage_buffers_per_shader_stage >= 2 && limits.max_compute_workgroups_per_dimension > 0
+}
+
+// The usage of a buffer of letter instances, the compute pass also reads it as a storage buffer when supported
+pub fn instance_buffer_usage(device: &wgpu::Device) -> wgpu::BufferUsages {
+    let usage = wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST;
+    if supported(device) {
+        usage | wgpu::BufferUsages::STORAGE
+    } else {
+        usage
+    }
+}
+
+pub struct ComputeDisplacement {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+    // The displaced instances of each model, grown to fit its instances
+    outputs: Vec<Option<wgpu::Buffer>>,
+}
+
+impl ComputeDisplacement {
+    // Check supported first, creating the pipeline fails without compute shaders.
+    // displacement_size is the size of the FocusPoints uniform the focus points are read from
+    pub fn new(device: &wgpu::Device, displacement_size: Option<wgpu::BufferSize>) -> Self {
+        let storage = |read_only| wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: storage(true),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: storage(false),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: displacement_size,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("displace_bind_group_layout"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("displace_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/displace.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("displace_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("displace_pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: Some("cs_main"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        ComputeDisplacement {
+            bind_group_layout,
+            pipeline,
+            outputs: vec![],
+        }
+    }
+
+    // Displaces the instances of every model. instances yields each model's instance buffer and instance count,
+    // and instance_size is the size of one instance in bytes. displacement is the buffer of focus points
+    pub fn dispatch<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        instances: impl ExactSizeIterator<Item = (&'a wgpu::Buffer, u32)>,
+        instance_size: u64,
+        displacement: &wgpu::Buffer,
+    ) {
+        self.outputs.resize(instances.len(), None);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("displace"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        for ((input, count), output) in instances.zip(&mut self.outputs) {
+            if count == 0 {
+                continue;
+            }
+            let size = count as u64 * instance_size;
+            if output.as_ref().is_none_or(|o| o.size() < size) {
+                *output = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("displaced_instance_buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+                    mapped_at_creation: false,
+                }));
+            }
+            let output = output.as_ref().unwrap();
+            // Bound to exactly count instances, the shader stops at arrayLength
+            fn binding(buffer: &wgpu::Buffer, size: u64) -> wgpu::BindingResource<'_> {
+                wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(size),
+                })
+            }
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: binding(input, size),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: binding(output, size),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: displacement.as_entire_binding(),
+                    },
+                ],
+                label: Some("displace_bind_group"),
+            });
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+    }
+
+    // The displaced instances of a model, None when the model had no instances to displace
+    pub fn output(&self, model_index: usize) -> Option<&wgpu::Buffer> {
+        self.outputs.get(model_index)?.as_ref()
+    }
+}
@@ -0,0 +1,163 @@
+// gpu_timer.rs
+//
+// Measures how long the gpu spends on the main render pass with timestamp queries. The pass writes a
+// timestamp when it begins and ends, the two are resolved into a buffer and read back a few frames later,
+// once the gpu is done with them. Needs Features::TIMESTAMP_QUERY, which WebGL2 doesn't have.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+// The states of the readback buffer's mapping
+const UNMAPPED: u8 = 0;
+const MAPPED: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+// The begin and end timestamps
+const QUERY_COUNT: u32 = 2;
+const QUERIES_SIZE: u64 = QUERY_COUNT as u64 * std::mem::size_of::<u64>() as u64;
+
+pub fn supported(device: &wgpu::Device) -> bool {
+    device.features().contains(wgpu::Features::TIMESTAMP_QUERY)
+}
+
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // Nanoseconds per timestamp tick
+    period: f32,
+    // Set once the timestamps were copied into readback_buffer and it's being mapped. Frames in the
+    // meantime still write timestamps, they just aren't read
+    pending: bool,
+    map_state: Arc<AtomicU8>,
+    // The last measured time in milliseconds
+    last_time: Option<f32>,
+}
+
+impl GpuTimer {
+    // Check supported first, creating the query set fails without TIMESTAMP_QUERY
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        GpuTimer {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("gpu_timer_query_set"),
+                ty: wgpu::QueryType::Timestamp,
+                count: QUERY_COUNT,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("gpu_timer_resolve_buffer"),
+                size: QUERIES_SIZE,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("gpu_timer_readback_buffer"),
+                size: QUERIES_SIZE,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+            pending: false,
+            map_state: Arc::new(AtomicU8::new(UNMAPPED)),
+            last_time: None,
+        }
+    }
+
+    // For the timestamp_writes of the pass to measure
+    pub fn timestamp_writes(&self) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    // Records copying this frame's timestamps out, after the measured pass. Skipped while the last ones
+    // are still being read back
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.pending {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..QUERY_COUNT, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, QUERIES_SIZE);
+    }
+
+    // Call after submitting the encoder resolve was recorded into, starts reading the timestamps back
+    pub fn submitted(&mut self) {
+        if self.pending {
+            return;
+        }
+        self.pending = true;
+        let map_state = self.map_state.clone();
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            map_state.store(if result.is_ok() { MAPPED } else { MAP_FAILED }, Ordering::Release);
+        });
+    }
+
+    // Picks up the timestamps once they've been read back, without waiting for the gpu
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        if !self.pending {
+            return;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        match self.map_state.load(Ordering::Acquire) {
+            MAPPED => {
+                let timestamps: [u64; 2] = bytemuck::pod_read_unaligned(&self.readback_buffer.slice(..).get_mapped_range());
+                self.readback_buffer.unmap();
+                // A pass that was reordered or clamped can end "before" it began
+                let ticks = timestamps[1].saturating_sub(timestamps[0]);
+                self.last_time = Some(ticks as f32 * self.period / 1_000_000.0);
+            }
+            MAP_FAILED => {}
+            _ => return,
+        }
+        self.map_state.store(UNMAPPED, Ordering::Release);
+        self.pending = false;
+    }
+
+    // How long the measured pass took on the gpu in milliseconds, None until the first measurement is read back
+    pub fn last_time(&self) -> Option<f32> {
+        self.last_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timed_pass_reads_back_a_time() {
+        // Only requested when the adapter has it, devices without it still get created
+        assert!(crate::platform_specific::device_descriptor(wgpu::Features::empty()).required_features.is_empty());
+        let Some((device, queue)) = crate::tests::test_device() else { return };
+        if !supported(&device) {
+            eprintln!("No TIMESTAMP_QUERY, skipping the test");
+            return;
+        }
+        let mut timer = GpuTimer::new(&device, &queue);
+        let target = crate::texture::GpuTexture::render_target(&device, wgpu::TextureFormat::Rgba8Unorm, 16, 16, "timed_target");
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("timed_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target.view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: Some(timer.timestamp_writes()),
+            occlusion_query_set: None,
+        });
+        timer.resolve(&mut encoder);
+        queue.submit([encoder.finish()]);
+        timer.submitted();
+        for _ in 0..100 {
+            device.poll(wgpu::Maintain::Wait);
+            timer.poll(&device);
+            if timer.last_time().is_some() {
+                break;
+            }
+        }
+        let time = timer.last_time().expect("The timestamps were never read back");
+        assert!(time >= 0.0 && time.is_finite());
+    }
+}
@@ -9,26 +9,13 @@
 
 use crate::texture;
 
-use rand_pcg::rand_core::{SeedableRng, RngCore};
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct Vert {
-    position: [f32; 3],
-    tex_coords: [f32; 2],
-}
+pub use glyph_core::model::{Vert, Model, Bounds, IndexOverflow};
+use glyph_core::model::{mirror_x, mirror_y, mirror_forward_slash, mirror_back_slash};
 
-impl Vert {
-    fn new_white(position: [f32; 3]) -> Self {
-        Vert {
-            position,
-            tex_coords: [position[0], position[1]],
-        }
-    }
-}
+use rand_pcg::rand_core::{SeedableRng, RngCore};
 
 //The vertex buffer desc of Vert
-const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x3];
 pub fn desc() -> wgpu::VertexBufferLayout<'static>{
     wgpu::VertexBufferLayout {
         array_stride: std::mem::size_of::<Vert>() as wgpu::BufferAddress,
@@ -37,184 +24,86 @@ pub fn desc() -> wgpu::VertexBufferLayout<'static>{
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct Model {
-    pub verts: Vec<Vert>,
-    pub tri_idxs: Vec<[u16; 3]>,
+// Why an OBJ file couldn't be loaded with load_obj_models
+#[derive(Debug)]
+pub enum ObjError {
+    Parse(tobj::LoadError),
+    // An object has more verts than the u16 index buffers can address
+    IndexOverflow(IndexOverflow),
 }
 
-impl Model {
-
-    pub fn number_indices(&self) -> u32 {
-        self.tri_idxs.len() as u32 * 3
-    }
-
-    // Takes in verts and indices, except the verts are only the x and y
-    fn new_2d(vs: &[(f32, f32)], ts: &[[u16; 3]]) -> Self {
-        let mut verts: Vec<Vert> = vec![];
-        for &(x, y) in vs {
-            verts.push(Vert::new_white([x, y, 0.0]));
-        }
-        let mut tri_idxs: Vec<[u16; 3]> = vec![];
-        for &t in ts {
-            tri_idxs.push(t);
-        }
-        Model {
-            verts,
-            tri_idxs,
-        }
-    }
-
-    // Supply the verts in counter-clockwise order so the tri points the right way
-    fn tri_2d(vs: [(f32, f32); 3]) -> Self {
-        Self::new_2d(&vs, &[[0, 1, 2]])
-    }
-
-    // Create a Model (and it's indexed tris) from a 2d tristrip
-    // The first 3 verts must form a counter-clockwise tri, then the rest of the verts will follow
-    // in a zig-zag fashion
-    fn tristrip_2d(vs: &[(f32, f32)]) -> Self {
-        let mut indices: Vec<[u16; 3]> = vec![];
-        let mut flip = false;
-        // Every other tri must be flipped for the tristrip to be the right direction
-        for i in 0u16..(vs.len()-2) as u16 {
-            if flip {
-                indices.push([i, i+2, i+1]);
-            } else {
-                indices.push([i, i+1, i+2])
-            }
-            flip = !flip;
-        }
-        Self::new_2d(&vs, indices.as_slice())
-    }
-
-    fn append_tri_2d(self, vs: [(f32, f32); 3]) -> Self {
-        self.append(Self::tri_2d(vs))
-    }
-
-    // Supply the verts in counter-clockwise order so the tris point the right way
-    fn rect_2d(vs: [(f32, f32); 4]) -> Self {
-        Self::new_2d(
-            &vs,
-            &[
-                [0, 1, 3],
-                [1, 2, 3],
-            ],
-        )
-    }
-
-    fn _subdivided_rect(subdivisions: u32, vs: [(f32, f32); 4]) -> Self {
-        let _ = (subdivisions, vs);
-        todo!()
-    }
-
-    fn append_rect_2d(self, vs: [(f32, f32); 4]) -> Self {
-        self.append(Self::rect_2d(vs))
-    }
-
-    // Appends self with f applied to a copy of self
-    fn append_apply(self, f: impl FnOnce(Model) -> Model) -> Self{
-        let clone = self.clone();
-        self.append(f(clone))
-    }
-
-    // Apply must change the indices appropriately to work with the right verts
-    // TODO: optimize model by checking if a vert is used already, combine those if possible
-    fn append(mut self, mut m: Model) -> Self {
-        //Correct m's indices by adding the len of self.verts
-        for tri_idx in &mut m.tri_idxs {
-            for idx in tri_idx {
-                *idx += self.verts.len() as u16;
-            }
-        }
-        self.tri_idxs.append(&mut m.tri_idxs);
-        self.verts.append(&mut m.verts);
-        self
-    }
-    
-    //Flips the triangle so it's pointing in the opposite direction
-    fn flip(mut self) -> Self {
-        for idx in &mut self.tri_idxs {
-            *idx = [
-                idx[0],
-                idx[2],
-                idx[1],
-            ];
-        }
-        self
-    }
-
-    fn mult(self, x: f32, y: f32, z: f32) -> Model {
-        self.vert_mod( |arr| [x * arr[0], y * arr[1], z * arr[2]] )
-    }
-
-    // uses function f on all the vert positions
-    fn vert_mod<F>(mut self, f: F) -> Self 
-    where F: Fn([f32;3]) -> [f32;3] {
-        for vert in &mut self.verts {
-            *vert = Vert{ position: f(vert.position), tex_coords: vert.tex_coords }
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ObjError::Parse(e) => write!(f, "Couldn't parse the OBJ file: {e}"),
+            ObjError::IndexOverflow(e) => e.fmt(f),
         }
-        self
     }
+}
 
-    // Resets the texture coordinates to = the x+0.5 and y vertex positions
-    // Use only when the model x and y coords are within x=[-0.5,0.5] and y=[0,1],
-    // unless you actually want clamping/wrapping on the texture
-    fn reset_tex_coords(mut self) -> Self {
-        for vert in &mut self.verts {
-            vert.tex_coords = [vert.position[0] + 0.5, vert.position[1]];
+impl std::error::Error for ObjError {}
+
+// Loads each object of an OBJ file (e.g. exported from Blender and embedded with include_str!) as a Model,
+// to use in place of the hand made letters in InitContent::alphabet_models. Returns each object's name and
+// model in file order. Faces are triangulated. Tex coords default to the xy position like the hand made
+// letters, normals are computed when the file has none. Materials are ignored
+pub fn load_obj_models(obj: &str) -> Result<Vec<(String, Model)>, ObjError> {
+    let mut reader = std::io::BufReader::new(obj.as_bytes());
+    // GPU_LOAD_OPTIONS gives a single index per vert, so positions, tex coords and normals line up
+    let (objects, _) = tobj::load_obj_buf(&mut reader, &tobj::GPU_LOAD_OPTIONS, |_| Err(tobj::LoadError::OpenFileFailed))
+        .map_err(ObjError::Parse)?;
+    objects.into_iter().map(|object| {
+        let mesh = object.mesh;
+        let vert_count = mesh.positions.len() / 3;
+        if vert_count > u16::MAX as usize + 1 {
+            return Err(ObjError::IndexOverflow(IndexOverflow { vert_count }));
         }
-        self
-    }
-
-    // Deduplicates vertices. Remember to check for 0.0 == -0.0
-    fn _optimizing_pass(self) -> Model {
-        todo!()
-    }
-
-    //Pass in a list of the exterior edges to extrude, or can I automatically detect exterior
-    //edges?
-    fn _extrude(self) -> Model {
-        todo!()
-    }
+        let verts = (0..vert_count).map(|i| {
+            let position = [mesh.positions[3 * i], mesh.positions[3 * i + 1], mesh.positions[3 * i + 2]];
+            let tex_coords = match mesh.texcoords.get(2 * i..2 * i + 2) {
+                Some(&[u, v]) => [u, v],
+                _ => [position[0], position[1]],
+            };
+            let normal = match mesh.normals.get(3 * i..3 * i + 3) {
+                Some(&[x, y, z]) => [x, y, z],
+                _ => [0.0, 0.0, 1.0],
+            };
+            Vert::new(position, tex_coords, normal)
+        }).collect();
+        let tri_idxs = mesh.indices.chunks_exact(3).map(|tri| [tri[0] as u16, tri[1] as u16, tri[2] as u16]).collect();
+        let model = Model { verts, tri_idxs };
+        let model = if mesh.normals.is_empty() { model.compute_normals() } else { model };
+        Ok((object.name, model))
+    }).collect()
 }
 
-fn mirror_x(m: Model) -> Model {
-    m.flip().mult(-1.0, 1.0, 1.0)
-}
-    
-fn mirror_y(m: Model) -> Model {
-    m.flip().vert_mod(|arr| [arr[0], ((arr[1] - 0.5) * -1.0) + 0.5, arr[2]])
-}
+// The stroke weight the letters were designed at
+pub const DEFAULT_STROKE_WEIGHT: f32 = 1.0;
 
-// mirror over '/'
-fn mirror_forward_slash(m: Model) -> Model {
-    m.flip().vert_mod(|arr| [arr[1] - 0.5, arr[0] + 0.5, arr[2]])
-}
-// mirror over '\'
-fn mirror_back_slash(m: Model) -> Model {
-    m.flip().vert_mod(|arr| [0.5 - arr[1], 0.5 - arr[0], arr[2]])
-}
-//
-//fn mirror_z(self) -> Self {
-//    self.flip().mult(1.0, 1.0, -1.0)
-//}
+// The letters a-z followed by the digits 0-9
+pub const GLYPH_COUNT: usize = 36;
+
+// Returns the GLYPH_COUNT glyph models, a-z then 0-9.
+// stroke_weight scales the width of the straight strokes, making the whole alphabet lighter or heavier
+pub fn create_alphabet_models(stroke_weight: f32) -> Vec<Model> {
+    // The width of a stroke, and the inner edge of a stem on the left side of a letter
+    let stroke = 0.2 * stroke_weight;
+    let stem_inner = -0.5 + stroke;
 
-pub fn create_alphabet_models() -> Vec<Model> {
     // Helper models
     let vertical_line = Model::tristrip_2d(&[
         (-0.5, 0.0),
-        (-0.3, 0.0),
+        (stem_inner, 0.0),
         (-0.5, 0.2),
-        (-0.3, 0.2),
+        (stem_inner, 0.2),
         (-0.5, 0.4),
-        (-0.3, 0.4),
+        (stem_inner, 0.4),
         (-0.5, 0.6),
-        (-0.3, 0.6),
+        (stem_inner, 0.6),
         (-0.5, 0.8),
-        (-0.3, 0.8),
+        (stem_inner, 0.8),
         (-0.5, 1.0),
-        (-0.3, 1.0),
+        (stem_inner, 1.0),
     ]);
     let vertical_line_thick = vertical_line.clone().vert_mod(|a| [(a[0] + 0.5) * 1.5 - 0.5, a[1], a[2]]);
     // Arc with dimensions x=[0.15, 0.5], y=[0.0, 0.35]
@@ -225,6 +114,44 @@ pub fn create_alphabet_models() -> Vec<Model> {
         (0.25, 0.25),
         (0.4, 0.10),
     ]).flip().append_apply(mirror_back_slash);
+    // The curved corners of the right side of an O, x=[0.15, 0.5]
+    let round_corners = arc.clone().append_apply(mirror_y);
+    // The right side of an O
+    let round_side = round_corners.clone().append(
+        Model::tristrip_2d(&[
+            (0.3,0.35),
+            (0.5,0.35),
+            (0.3,0.5),
+            (0.5,0.5),
+            (0.3,0.65),
+            (0.5,0.65),
+        ])
+    );
+    // The bottom of an O, x=[-0.15, 0.15]
+    let round_bottom = Model::tristrip_2d(
+        &[
+            (-0.15,0.2),
+            (-0.15, 0.0),
+            (0.0, 0.2),
+            (0.0, 0.0),
+            (0.15, 0.2),
+            (0.15, 0.0),
+        ]
+    );
+    // The top and bottom of an O
+    let round_bars = round_bottom.clone().append_apply(mirror_y);
+    // A stem in the middle of the letter
+    let center_line = vertical_line.clone().vert_mod(|a| [a[0] + 0.5 - 0.5 * stroke, a[1], a[2]]);
+    // A horizontal bar across x=[left, right] at the bottom or top of the letter
+    let bottom_bar = |left: f32, right: f32| Model::strip_2d([(left, 0.0), (left, stroke)], [(right, 0.0), (right, stroke)], 4);
+    let top_bar = |left: f32, right: f32| mirror_y(bottom_bar(left, right));
+    // A slanted stroke with horizontal ends, from the bottom edge's left x to the top edge's left x
+    let slant = |bottom: (f32, f32), top: (f32, f32)| {
+        let width = 1.25 * stroke;
+        Model::strip_2d([bottom, (bottom.0 + width, bottom.1)], [top, (top.0 + width, top.1)], 4)
+    };
+    // The right side of a U, its curved bottom corner and straight side up to the top
+    let u_side = arc.clone().append(Model::strip_2d([(0.3, 0.35), (0.5, 0.35)], [(0.3, 1.0), (0.5, 1.0)], 3));
 
     // Letter models
     let v = Model::rect_2d( // Diagonal part of V
@@ -249,7 +176,9 @@ pub fn create_alphabet_models() -> Vec<Model> {
             (-0.25, 0.45),
         ]
     );
-    let c = Model::new_2d(&[], &[]);
+    let c = mirror_x(round_side.clone()) // An O opened on the right, keeping the curved ends
+        .append(round_corners.clone())
+        .append(round_bars.clone());
     let d = vertical_line_thick.clone().append(arc.clone().vert_mod(
         |a| [(a[0] - 0.15) / 0.35 * 0.6 - 0.2, a[1], a[2]]
     ).append(Model::tristrip_2d(&[
@@ -264,88 +193,74 @@ pub fn create_alphabet_models() -> Vec<Model> {
             d.clone()
             .vert_mod(|v| [v[0], v[1] * 0.5, v[2]])
         );
-    let e = Model::tristrip_2d( // The horizontal E parts
+    let e_bottom = Model::tristrip_2d( // The bottom horizontal E part
         &[
             (0.5, 0.0),
-            (0.5, 0.2),
+            (0.5, stroke),
             (0.25, 0.0),
-            (0.25, 0.2),
+            (0.25, stroke),
             (0.0, 0.0),
-            (0.0, 0.2),
+            (0.0, stroke),
             (-0.25, 0.0),
-            (-0.25, 0.2),
-            (-0.3, 0.0),
-            (-0.3, 0.2),
+            (-0.25, stroke),
+            (stem_inner, 0.0),
+            (stem_inner, stroke),
         ]
-    ).append_apply(mirror_y).append( // The middle horizontal E part
-        Model::tristrip_2d(&[
-            (0.5, 0.4),
-            (0.5, 0.6),
-            (0.25, 0.4),
-            (0.25, 0.6),
-            (0.0, 0.4),
-            (0.0, 0.6),
-            (-0.3, 0.4),
-            (-0.3, 0.6),
-        ])
-    ).append( // The Vertical E part
-        vertical_line.clone()
     );
-    let f = Model::new_2d(&[], &[]); //F shares parts with E
-    let g = Model::new_2d(&[], &[]);
+    let e_middle = Model::tristrip_2d(&[ // The middle horizontal E part
+        (0.5, 0.5 - 0.5 * stroke),
+        (0.5, 0.5 + 0.5 * stroke),
+        (0.25, 0.5 - 0.5 * stroke),
+        (0.25, 0.5 + 0.5 * stroke),
+        (0.0, 0.5 - 0.5 * stroke),
+        (0.0, 0.5 + 0.5 * stroke),
+        (stem_inner, 0.5 - 0.5 * stroke),
+        (stem_inner, 0.5 + 0.5 * stroke),
+    ]);
+    let e = e_bottom.clone()
+        .append_apply(mirror_y) // The top horizontal E part
+        .append(e_middle.clone())
+        .append(vertical_line.clone()); // The Vertical E part
+    let f = mirror_y(e_bottom.clone()) // F is E without the bottom part
+        .append(e_middle.clone())
+        .append(vertical_line.clone());
+    let g = c.clone() // C with its lower right side and an inner bar
+        .append(Model::strip_2d([(0.3, 0.35), (0.5, 0.35)], [(0.3, 0.5), (0.5, 0.5)], 1))
+        .append(Model::strip_2d([(0.0, 0.5 - stroke), (0.0, 0.5)], [(0.5, 0.5 - stroke), (0.5, 0.5)], 2));
     let h = vertical_line_thick.clone( // Vertical part of H
     ).append_apply(mirror_x).append( // Horizontal part of H
         Model::tristrip_2d(&[
-            (-0.2, 0.6),
-            (-0.2, 0.4),
-            (0.0, 0.6),
-            (0.0, 0.4),
-            (0.2, 0.6),
-            (0.2, 0.4),
+            (-0.2, 0.5 + 0.5 * stroke),
+            (-0.2, 0.5 - 0.5 * stroke),
+            (0.0, 0.5 + 0.5 * stroke),
+            (0.0, 0.5 - 0.5 * stroke),
+            (0.2, 0.5 + 0.5 * stroke),
+            (0.2, 0.5 - 0.5 * stroke),
         ])
     );
-    let i = Model::new_2d(&[], &[]);
-    let j = Model::new_2d(&[], &[]);
-    let k = Model::new_2d(&[], &[]);
+    let i = center_line.clone().append(bottom_bar(-0.3, 0.3)).append(top_bar(-0.3, 0.3));
+    let j = u_side.clone().append(mirror_x(arc.clone())).append(round_bottom.clone()); // U without its left side
+    let k = vertical_line_thick.clone()
+        .append(slant((-0.2, 0.35), (0.5 - 1.25 * stroke, 1.0))) // Upper arm
+        .append(slant((0.5 - 1.25 * stroke, 0.0), (-0.05, 0.55))); // Lower leg
     let l = Model::tristrip_2d( // The horizontal L portion
         &[
             (0.5, 0.0),
-            (0.5, 0.2),
+            (0.5, stroke),
             (0.25, 0.0),
-            (0.25, 0.2),
+            (0.25, stroke),
             (0.0, 0.0),
-            (0.0, 0.2),
+            (0.0, stroke),
             (-0.25, 0.0),
-            (-0.25, 0.2),
-            (-0.3, 0.0),
-            (-0.3, 0.2),
+            (-0.25, stroke),
+            (stem_inner, 0.0),
+            (stem_inner, stroke),
             (-0.5, 0.0),
         ]
     ).append_apply(mirror_forward_slash);
     // m will be done at a later line
-    let n = Model::new_2d(&[], &[]);
-    let o = arc.clone( // The diagonal part of the O
-    ).append_apply(mirror_y).append( // The vertical part of the O
-        Model::tristrip_2d(&[
-            (0.3,0.35),
-            (0.5,0.35),
-            (0.3,0.5),
-            (0.5,0.5),
-            (0.3,0.65),
-            (0.5,0.65),
-        ])
-    ).append_apply(mirror_x).append(
-        Model::tristrip_2d( // The horizontal part of the O
-            &[
-                (-0.15,0.2),
-                (-0.15, 0.0),
-                (0.0, 0.2),
-                (0.0, 0.0),
-                (0.15, 0.2),
-                (0.15, 0.0),
-            ]
-        ).append_apply(mirror_y)
-    );
+    let n = vertical_line_thick.clone().append_apply(mirror_x).append(slant((0.5 - 1.25 * stroke, 0.0), (-0.5, 1.0)));
+    let o = round_side.clone().append_apply(mirror_x).append(round_bars.clone());
     let p = arc.clone().vert_mod(
         |a| [(a[0] - 0.15) / 0.35 * 0.6 - 0.2, a[1] + 0.15, a[2]]
     ).append_apply(mirror_y).vert_mod(
@@ -366,7 +281,7 @@ pub fn create_alphabet_models() -> Vec<Model> {
         (-0.5, 1.0),
         (-0.2, 1.0),
     ]));
-    let q = Model::new_2d(&[], &[]);
+    let q = o.clone().append(slant((0.5 - 1.25 * stroke, 0.0), (0.0, 0.4))); // O with a tail
     let r = p.clone().append(Model::tristrip_2d(&[
         (-0.02857, 0.32),
         (-0.2, 0.3),
@@ -377,9 +292,15 @@ pub fn create_alphabet_models() -> Vec<Model> {
         (0.4, 0.0),
         (0.1, 0.0),
     ]));
-    let s = Model::new_2d(&[], &[]);
-    let t = Model::new_2d(&[], &[]);
-    let u = Model::new_2d(&[], &[]);
+    // The top half of S is C without its lower right corner squashed into the top half, and the
+    // bottom half is the top half turned around
+    let s = mirror_x(round_side.clone())
+        .append(round_bars.clone())
+        .append(mirror_y(arc.clone()))
+        .vert_mod(|a| [a[0], 0.5 + 0.5 * a[1], a[2]])
+        .append_apply(|half| half.vert_mod(|a| [-a[0], 1.0 - a[1], a[2]]));
+    let t = center_line.clone().append(top_bar(-0.5, 0.5));
+    let u = u_side.clone().append_apply(mirror_x).append(round_bottom.clone());
     let w = Model::tristrip_2d(
         &[
             (0.0, 1.0),
@@ -402,18 +323,103 @@ pub fn create_alphabet_models() -> Vec<Model> {
             (0.5, 1.0),
         ])
     ).flip().append_apply(mirror_x);
-    let x = Model::new_2d(&[], &[]);
-    let y = Model::new_2d(&[], &[]);
-    let z = Model::new_2d(&[], &[]);
+    let x = slant((-0.5, 0.0), (0.5 - 1.25 * stroke, 1.0)).append_apply(mirror_x);
+    let y = slant((-0.625 * stroke, 0.45), (-0.5, 1.0)) // The arms of Y meet over the stem
+        .append_apply(mirror_x)
+        .append(center_line.clone().vert_mod(|a| [a[0], 0.45 * a[1], a[2]]));
+    let z = bottom_bar(-0.5, 0.5)
+        .append(top_bar(-0.5, 0.5))
+        .append(slant((-0.5, stroke), (0.5 - 1.25 * stroke, 1.0 - stroke)));
 
     let m = mirror_y(w.clone()); //Simply an upside down M
 
-    vec![a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q, r, s, t, u, v, w, x, y, z]
+    // Digit models
+    // The top of an O, y=[0.65, 1.0]
+    let arch = mirror_y(arc.clone()).append_apply(mirror_x).append(mirror_y(round_bottom.clone()));
+    // The right side and top and bottom of an O, with the bars carried on to the left
+    let bowl = round_side.clone().append(round_bars.clone()).append(
+        Model::strip_2d([(-0.4, 0.0), (-0.4, 0.2)], [(-0.15, 0.0), (-0.15, 0.2)], 1).append_apply(mirror_y)
+    );
+    let squash = |model: Model, bottom: f32, top: f32| model.vert_mod(|a| [a[0], bottom + (top - bottom) * a[1], a[2]]);
+    let zero = o.clone().mult(0.8, 1.0, 1.0);
+    let one = center_line.clone()
+        .append(bottom_bar(-0.3, 0.3))
+        .append(slant((-0.4, 0.75), (-0.1, 1.0))); // The flag at the top
+    let two = arch.clone()
+        .append(Model::strip_2d([(0.3, 0.5), (0.5, 0.5)], [(0.3, 0.65), (0.5, 0.65)], 1))
+        .append(slant((-0.5, stroke), (0.5 - 1.25 * stroke, 0.55)))
+        .append(bottom_bar(-0.5, 0.5));
+    let three = squash(bowl.clone(), 0.5, 1.0).append(squash(bowl.clone(), 0.0, 0.5));
+    let four = vertical_line.clone().vert_mod(|a| [a[0] + 0.65, a[1], a[2]]) // The stem
+        .append(Model::strip_2d([(-0.5, 0.3), (-0.5, 0.3 + stroke)], [(0.5, 0.3), (0.5, 0.3 + stroke)], 4))
+        .append(slant((-0.5, 0.3 + stroke), (0.15, 1.0)));
+    let five = squash(bowl.clone(), 0.0, 0.6)
+        .append(top_bar(-0.5, 0.5))
+        .append(squash(vertical_line.clone(), 0.5, 1.0));
+    let six = c.clone().append(squash(o.clone(), 0.0, 0.6)); // A C with a loop at the bottom
+    let seven = top_bar(-0.5, 0.5).append(slant((-0.2, 0.0), (0.5 - 1.25 * stroke, 1.0 - stroke)));
+    let eight = squash(o.clone(), 0.0, 0.55).append(squash(o.clone().mult(0.85, 1.0, 1.0), 0.45, 1.0));
+    let nine = six.clone().vert_mod(|a| [-a[0], 1.0 - a[1], a[2]]); // A 6 turned around
+
+    vec![
+        a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q, r, s, t, u, v, w, x, y, z,
+        zero, one, two, three, four, five, six, seven, eight, nine,
+    ]
         .into_iter()
-        .map(|l| l.reset_tex_coords())
+        .map(|l| l.reset_tex_coords().optimizing_pass())
         .collect()
 }
 
+// Whether each glyph has any geometry yet, the rest are still empty models
+pub fn implemented_glyphs() -> [bool; GLYPH_COUNT] {
+    let models = create_alphabet_models(DEFAULT_STROKE_WEIGHT);
+    std::array::from_fn(|i| !models[i].verts.is_empty())
+}
+
+// Where a letter's cell sits in a baked atlas texture, in texture coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRegion {
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+// The layout of a baked atlas. regions[i] is the cell of the letter with letter index i
+#[derive(Clone, Debug)]
+pub struct Atlas {
+    pub cell_size: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub regions: Vec<AtlasRegion>,
+}
+
+// Rasterizes the glyph models into cell_size * cell_size cells of one texture, white with the
+// coverage in alpha. Text can then be drawn as textured quads instead of instanced letter meshes
+pub fn bake_alphabet_atlas(device: &wgpu::Device, queue: &wgpu::Queue, cell_size: u32) -> (texture::GpuTexture, Atlas) {
+    let models = create_alphabet_models(DEFAULT_STROKE_WEIGHT);
+    let columns = (models.len() as f32).sqrt().ceil() as u32;
+    let rows = (models.len() as u32).div_ceil(columns);
+    let mut tex = texture::RgbaTexture::solid(columns * cell_size, rows * cell_size, [0u8; 4], wgpu::TextureFormat::Rgba8UnormSrgb);
+
+    let mut regions = vec![];
+    for (i, model) in models.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let coverage = model.rasterize(cell_size, cell_size);
+        for y in 0..cell_size {
+            for x in 0..cell_size {
+                let alpha = coverage[(y * cell_size + x) as usize];
+                tex.set_pixel(column * cell_size + x, row * cell_size + y, [255, 255, 255, alpha]);
+            }
+        }
+        regions.push(AtlasRegion {
+            uv_min: [column as f32 / columns as f32, row as f32 / rows as f32],
+            uv_max: [(column + 1) as f32 / columns as f32, (row + 1) as f32 / rows as f32],
+        });
+    }
+
+    let gpu_texture = texture::GpuTexture::from_rgbatexture(&tex, device, queue, "alphabet_atlas", true, false, texture::SamplerConfig::default());
+    (gpu_texture, Atlas { cell_size, columns, rows, regions })
+}
+
 const SIZE: usize = 512;
 
 pub fn _create_letter_texture() -> texture::RgbaTexture<[u8; 4]> {
@@ -448,7 +454,7 @@ pub fn create_pixelated_letter_texture() -> texture::RgbaTexture<[u8; 4]> {
             tex.set_pixel(x, y, [
                 (gradient as f32 * mul) as u8,
                 (gradient as f32 * mul) as u8,
-                (100 as f32 * mul) as u8,
+                (100.0 * mul) as u8,
                 255
             ]);
         }
@@ -456,32 +462,47 @@ pub fn create_pixelated_letter_texture() -> texture::RgbaTexture<[u8; 4]> {
     tex
 }
 
-pub fn create_static_texture(chunk_size: u32) -> texture::RgbaTexture<[u8; 4]> {
-    create_fractal_static_texture(chunk_size, chunk_size)
+// Noise that makes a bumpy normal map, see State::set_normal_map. The same seed always gives the same noise
+pub fn create_static_texture(chunk_size: u32, seed: u64) -> texture::RgbaTexture<[u8; 4]> {
+    create_fractal_static_texture_seeded(chunk_size, chunk_size, seed)
 }
 
 fn f_to_c(f: f32) -> u8 {
     (f * 255.0) as u8
 }
 
-// uses next_u32, so only works properly in ranges of length < 2^32
+// Uniform over the range. The top 53 bits of a u64 exactly fill an f64's mantissa, giving an unbiased
+// [0, 1) that's only rounded once, when the result is cast to f32
 fn random_range<T: rand_pcg::rand_core::RngCore>(r: &mut T, range: std::ops::Range<f32>) -> f32 {
-    let num = r.next_u32();
-    let length = range.end - range.start;
-    (num as f32 * length / u32::MAX as f32) + range.start
+    let unit = (r.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+    let start = range.start as f64;
+    let length = range.end as f64 - start;
+    (start + unit * length) as f32
 }
 
+// Blends val into the chunk as old / olddiv + val / div. Summed in f32 so the low bits aren't lost to integer
+// division, and clamped so a bright chunk can't wrap around. The chunk ends up opaque
 fn add_chunk(tex: &mut texture::RgbaTexture<[u8; 4]>, x: u32, y: u32, val: [u8; 4], chunk_size: u32, olddiv: u8, div: u8) {
+    let blend = |old: u8, new: u8| (old as f32 / olddiv as f32 + new as f32 / div as f32).round().min(255.0) as u8;
     for i in 0..chunk_size {
         for j in 0..chunk_size {
             let oldval = tex.get_pixel(x+i, y+j);
-            let outval = [oldval[0]/olddiv + val[0]/div, oldval[1]/olddiv + val[1]/div, oldval[2]/olddiv + val[2]/div, 0];
+            let outval = [blend(oldval[0], val[0]), blend(oldval[1], val[1]), blend(oldval[2], val[2]), 255];
             tex.set_pixel(x+i, y+j, outval);
         }
     }
 }
 
+// The seed the letter texture's static has always used
+pub const DEFAULT_STATIC_SEED: u64 = 1;
+
 pub fn create_fractal_static_texture(start_chunk_size: u32, end_chunk_size: u32) -> texture::RgbaTexture<[u8; 4]> {
+    create_fractal_static_texture_seeded(start_chunk_size, end_chunk_size, DEFAULT_STATIC_SEED)
+}
+
+// Static in chunks from start_chunk_size halving down to end_chunk_size, each level fainter than the last.
+// The same seed always gives the same texture
+pub fn create_fractal_static_texture_seeded(start_chunk_size: u32, end_chunk_size: u32, seed: u64) -> texture::RgbaTexture<[u8; 4]> {
     let mut tex = texture::RgbaTexture::<[u8; 4]> {
         values: Vec::with_capacity(SIZE * SIZE),
         format: wgpu::TextureFormat::Rgba8Unorm,
@@ -512,7 +533,173 @@ pub fn create_fractal_static_texture(start_chunk_size: u32, end_chunk_size: u32)
     }
 
 
-    let mut rng = rand_pcg::Pcg32::seed_from_u64(1);
+    let mut rng = rand_pcg::Pcg32::seed_from_u64(seed);
     recurse(&mut rng, &mut tex, start_chunk_size, end_chunk_size, 2);
     tex
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The index of a letter in the alphabet models
+    fn glyph(c: char) -> usize {
+        (c as u8 - b'a') as usize
+    }
+
+    #[test]
+    fn heavier_stroke_widens_the_h_stem() {
+        // The inner edge of h's left stem, where it meets the top of the letter
+        let stem_inner = |stroke_weight: f32| {
+            let h = &create_alphabet_models(stroke_weight)[glyph('h')];
+            h.verts.iter()
+                .map(|v| v.position())
+                .filter(|p| p[1] == 1.0 && p[0] < 0.0 && p[0] > -0.5)
+                .map(|p| p[0])
+                .fold(f32::MIN, f32::max)
+        };
+        assert!((stem_inner(1.0) - -0.2).abs() < 1e-5);
+        assert!((stem_inner(1.5) - -0.05).abs() < 1e-5);
+    }
+
+    #[test]
+    fn atlas_has_a_nonempty_cell_per_glyph() {
+        let Some((device, queue)) = crate::tests::test_device() else { return };
+        let cell_size = 16;
+        let (gpu_texture, atlas) = bake_alphabet_atlas(&device, &queue, cell_size);
+        assert_eq!(atlas.regions.len(), GLYPH_COUNT);
+        assert!(atlas.columns * atlas.rows >= GLYPH_COUNT as u32);
+
+        let bytes = texture::read_texture(&device, &queue, device.create_command_encoder(&Default::default()), &gpu_texture.texture);
+        let pixels: &[[u8; 4]] = bytemuck::cast_slice(&bytes);
+        let width = atlas.columns * cell_size;
+        for (i, region) in atlas.regions.iter().enumerate() {
+            let (left, top) = ((region.uv_min[0] * width as f32) as u32, (region.uv_min[1] * (atlas.rows * cell_size) as f32) as u32);
+            let covered = (top..top + cell_size)
+                .flat_map(|y| (left..left + cell_size).map(move |x| (x, y)))
+                .filter(|&(x, y)| pixels[(y * width + x) as usize][3] > 0)
+                .count();
+            assert!(covered > 0, "glyph {i}'s cell is empty");
+        }
+    }
+
+    #[test]
+    fn original_letters_are_implemented() {
+        let implemented = implemented_glyphs();
+        for c in ['a', 'h', 'e', 'l', 'o'] {
+            assert!(implemented[glyph(c)], "{c}");
+        }
+    }
+
+    #[test]
+    fn c_is_an_open_ccw_curve() {
+        let c = &create_alphabet_models(DEFAULT_STROKE_WEIGHT)[glyph('c')];
+        let coverage = c.rasterize(16, 16);
+        assert!(coverage.iter().any(|&p| p > 0));
+        // Every tri faces the camera, back-face culling would drop the others
+        for tri in &c.tri_idxs {
+            let [a, b, p] = tri.map(|i| c.verts[i as usize].position());
+            let area = (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0]);
+            assert!(area > 0.0, "{tri:?}");
+        }
+        // The curve is open on the right, unlike o's
+        let o = create_alphabet_models(DEFAULT_STROKE_WEIGHT)[glyph('o')].rasterize(16, 16);
+        let right_edge = |coverage: &[u8]| (0..16).map(|y| coverage[y * 16 + 15] as u32).sum::<u32>();
+        assert!(right_edge(&coverage) < right_edge(&o));
+    }
+
+    #[test]
+    fn f_is_e_without_the_bottom_bar() {
+        let models = create_alphabet_models(DEFAULT_STROKE_WEIGHT);
+        let f = &models[glyph('f')];
+        assert!(!f.verts.is_empty());
+        assert!(!f.tri_idxs.is_empty());
+        let bottom_right = |model: &Model| model.rasterize(16, 16)[15 * 16 + 12];
+        assert_eq!(bottom_right(f), 0);
+        assert!(bottom_right(&models[glyph('e')]) > 0);
+    }
+
+    #[test]
+    fn all_26_letters_have_geometry() {
+        let models = create_alphabet_models(DEFAULT_STROKE_WEIGHT);
+        for c in 'a'..='z' {
+            let model = &models[glyph(c)];
+            assert!(!model.tri_idxs.is_empty(), "{c} is empty");
+            assert!(model.rasterize(16, 16).iter().any(|&p| p > 0), "{c} covers no pixels");
+        }
+    }
+
+    #[test]
+    fn add_chunk_blends_without_wrapping() {
+        let mut tex = texture::RgbaTexture::solid(4, 4, [101u8, 255, 3, 0], wgpu::TextureFormat::Rgba8UnormSrgb);
+        add_chunk(&mut tex, 2, 2, [50, 255, 5, 9], 2, 2, 2);
+        // 101/2 + 50/2 = 75.5, 255/2 + 255/2 = 255 and 3/2 + 5/2 = 4, with an opaque alpha
+        for (x, y) in [(2, 2), (3, 2), (2, 3), (3, 3)] {
+            assert_eq!(tex.get_pixel(x, y), [76, 255, 4, 255]);
+        }
+        assert_eq!(tex.get_pixel(1, 2), [101, 255, 3, 0]);
+
+        // Weights adding up to more than 1 clamp instead of wrapping around
+        add_chunk(&mut tex, 0, 0, [200, 200, 200, 0], 1, 1, 1);
+        assert_eq!(tex.get_pixel(0, 0), [255, 255, 203, 255]);
+    }
+
+    #[test]
+    fn static_texture_is_reproducible_per_seed() {
+        let first = create_fractal_static_texture_seeded(16, 2, 7);
+        assert_eq!(first.values, create_fractal_static_texture_seeded(16, 2, 7).values);
+        assert_ne!(first.values, create_fractal_static_texture_seeded(16, 2, 8).values);
+        // The unseeded wrapper keeps the seed the texture always had
+        assert_eq!(create_fractal_static_texture(16, 2).values, create_fractal_static_texture_seeded(16, 2, DEFAULT_STATIC_SEED).values);
+        assert_eq!(create_static_texture(8, 3).values, create_fractal_static_texture_seeded(8, 8, 3).values);
+    }
+
+    #[test]
+    fn random_range_is_uniform() {
+        let mut rng = rand_pcg::Pcg32::seed_from_u64(42);
+        let samples: Vec<f32> = (0..100_000).map(|_| random_range(&mut rng, 0.0..1.0)).collect();
+        assert!(samples.iter().all(|s| (0.0..1.0).contains(s)));
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!((mean - 0.5).abs() < 0.005, "mean {mean}");
+
+        // Ranges past 2^32 are covered too
+        let wide: Vec<f32> = (0..1000).map(|_| random_range(&mut rng, -1e12..1e12)).collect();
+        assert!(wide.iter().any(|&s| s < -1e11) && wide.iter().any(|&s| s > 1e11));
+    }
+
+    #[test]
+    fn embedded_obj_loads_with_triangulated_faces() {
+        let obj = "\
+o square
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 3 4
+o wedge
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+f 5/1 6/2 7/3
+";
+        let models = load_obj_models(obj).unwrap();
+        let names: Vec<&str> = models.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["square", "wedge"]);
+
+        // The quad is split into 2 tris
+        let square = &models[0].1;
+        assert_eq!((square.verts.len(), square.tri_idxs.len(), square.number_indices()), (4, 2, 6));
+        // Without tex coords or normals, the tex coords are the xy position and the normals face +z
+        assert_eq!(square.verts[2].tex_coords(), [1.0, 1.0]);
+        assert!(square.verts.iter().all(|v| v.normal() == [0.0, 0.0, 1.0]));
+
+        let wedge = &models[1].1;
+        assert_eq!((wedge.verts.len(), wedge.number_indices()), (3, 3));
+        assert_eq!(wedge.verts[1].tex_coords(), [1.0, 0.0]);
+
+        assert!(load_obj_models("f 1 2 3\n").is_err());
+    }
+}
@@ -0,0 +1,4797 @@
+use cgmath::prelude::*;
+
+use std::sync::{Arc, Mutex};
+
+use winit::{
+    application::ApplicationHandler, event::WindowEvent, event_loop::{ActiveEventLoop, ControlFlow, EventLoop}, window::{Window, WindowId}
+};
+
+use wgpu::util::DeviceExt;
+
+mod platform_specific;
+pub mod letters;
+pub mod texture;
+mod taa;
+mod supersample;
+mod shadow;
+mod displace;
+mod gpu_timer;
+
+// The horizontal span text is laid out in
+const LEFT_BOUND: f32 = -10.0;
+const RIGHT_BOUND: f32 = 10.0;
+// How far past the bounds marquee letters travel before wrapping, so they're fully off screen
+const MARQUEE_MARGIN: f32 = 2.0;
+// Seconds for the displacement focus to close half of its distance to the cursor.
+// Matches the old fixed lerp of 0.05 per frame at 60 fps
+const DEFAULT_FOCUS_HALF_LIFE: f32 = 0.225;
+// Seconds for the displacement strength to halve once the cursor leaves the window.
+// Matches the old decay of 0.985 per frame at 60 fps
+const STRENGTH_DECAY_HALF_LIFE: f32 = 0.764;
+// While the cursor is on the window the strength plus STRENGTH_GROWTH_OFFSET doubles every
+// STRENGTH_GROWTH_DOUBLING seconds. Matches the old growth of * 1.02 + 0.002 per frame at 60 fps
+const STRENGTH_GROWTH_DOUBLING: f32 = 0.583;
+const STRENGTH_GROWTH_OFFSET: f32 = 0.1;
+// How much each frame moves the average frame time, higher reacts faster but jitters more
+const FRAME_TIME_SMOOTHING: f32 = 0.05;
+// Seconds between showing the frame rate
+const FRAME_RATE_INTERVAL: f32 = 1.0;
+// Frames in a row the surface can fail before rendering stops until the next resize
+const MAX_SURFACE_ERRORS: u32 = 10;
+// Seconds between checks for changed settings to save
+const SETTINGS_SAVE_INTERVAL: f32 = 1.0;
+// Radians the camera orbits by when dragging across half the window
+const ORBIT_SPEED: f32 = std::f32::consts::FRAC_PI_2;
+// Each line scrolled changes the camera's distance from its target by this factor
+const ZOOM_STEP: f32 = 1.1;
+// Trackpads scroll in pixels, this many count as one line
+const PIXELS_PER_LINE: f32 = 40.0;
+const MIN_ZOOM_RADIUS: f32 = 1.0;
+const MAX_ZOOM_RADIUS: f32 = 50.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Alignment {
+    // Every line is stretched to span the bounds, so the letters of a short line are larger
+    Stretch,
+    // Letters are placed at a fixed advance and the gaps between words grow so each line spans the
+    // bounds. The last line of a paragraph is left aligned instead
+    Justify,
+    // Letters are placed at a fixed advance and each line is centered between the bounds
+    Center,
+}
+
+// How the camera projects the scene. Orthographic has no foreshortening, so letters at the edges
+// aren't skewed and every letter is the same size
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Projection {
+    // Vertical field of view in degrees
+    Perspective { fovy: f32 },
+    // Height of the view in world units, the width follows the aspect ratio
+    Orthographic { height: f32 },
+}
+
+impl Projection {
+    // The other projection, showing the plane distance away from the camera at the same size
+    fn toggled(self, distance: f32) -> Self {
+        match self {
+            Projection::Perspective { fovy } => Projection::Orthographic {
+                height: 2.0 * distance * (fovy.to_radians() / 2.0).tan(),
+            },
+            Projection::Orthographic { height } => Projection::Perspective {
+                fovy: 2.0 * (height / (2.0 * distance)).atan().to_degrees(),
+            },
+        }
+    }
+}
+
+// One setting over the anti-aliasing techniques, State::set_anti_aliasing configures the matching subsystem
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AntiAliasing {
+    None,
+    // Multisampling with the given sample count
+    Msaa(u32),
+    // Signed distance field glyphs, not supported yet so it falls back to None
+    Sdf,
+    Taa,
+    // Renders at the given multiple of the surface resolution and downsamples
+    Supersample(f32),
+}
+
+// Lines drawn across each line of text, using the letters' color
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TextDecoration {
+    pub underline: bool,
+    pub strikethrough: bool,
+    // Relative to the height of the letters
+    pub thickness: f32,
+}
+
+impl Default for TextDecoration {
+    fn default() -> Self {
+        TextDecoration {
+            underline: false,
+            strikethrough: false,
+            thickness: 0.08,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GradientAxis {
+    // From the leftmost letter to the rightmost
+    Horizontal,
+    // From the top line to the bottom line
+    Vertical,
+}
+
+// Two colors blended across the whole block of text, each letter instance is tinted with the color at its center
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TextGradient {
+    pub start: [f32; 4],
+    pub end: [f32; 4],
+    pub axis: GradientAxis,
+}
+
+// Controls how get_letter_instances places a block of text in the world
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LayoutConfig {
+    // The z value every letter in the block is placed at
+    pub z_plane: f32,
+    pub alignment: Alignment,
+    // The distance between the centers of adjacent letters, when the alignment doesn't stretch letters
+    pub advance: f32,
+    // Extra space between adjacent letters on top of the advance, negative packs them tighter.
+    // Letters never move past each other, the spacing stops at 0.0
+    pub tracking: f32,
+    // The size of a letter relative to the line height, which is the advance unless stretched
+    pub scale_ratio: f32,
+    // Drops instances that duplicate another instance of the same letter before uploading them
+    pub dedup_instances: bool,
+    // Numbers each line in a gutter left of the text, right-aligned and spaced by advance
+    pub line_numbers: bool,
+    // Moves the block so the average position of its letters lands on this (x, y), None leaves it where
+    // the alignment puts it
+    pub center: Option<[f32; 2]>,
+    // Lines longer than this many chars are wrapped between words, None keeps every line as written
+    pub wrap: Option<usize>,
+    // Tints the letters, None leaves them white
+    pub color_gradient: Option<TextGradient>,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            z_plane: 0.0,
+            alignment: Alignment::Center,
+            advance: 2.0,
+            tracking: 0.0,
+            scale_ratio: 0.75,
+            dedup_instances: false,
+            line_numbers: false,
+            center: None,
+            wrap: None,
+            color_gradient: None,
+        }
+    }
+}
+
+impl LayoutConfig {
+    // The distance between the centers of adjacent letters in a line that isn't stretched
+    fn letter_step(&self) -> f32 {
+        (self.advance + self.tracking).max(0.0)
+    }
+}
+
+// The tweakable parameters of a State, without any of the gpu handles. Taken with State::config_snapshot
+// and restored with State::apply_config
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StateConfig {
+    pub text: String,
+    pub layout: LayoutConfig,
+    pub camera_eye: [f32; 3],
+    pub camera_target: [f32; 3],
+    pub projection: Projection,
+    pub displacement_focus: [f32; 2],
+    pub displacement_strength: f32,
+    // (top, bottom) colors, None uses the letter texture
+    pub gradient: Option<([f32; 4], [f32; 4])>,
+    pub decoration: TextDecoration,
+    pub marquee_speed: f32,
+    pub debug_colors: bool,
+    pub show_bounds: bool,
+    pub anti_aliasing: AntiAliasing,
+    // rgba
+    pub clear_color: [f64; 4],
+    // The blur radius in pixels, None draws no shadow
+    pub drop_shadow: Option<f32>,
+    // (color, width), None draws no outline. See State::set_outline_style
+    pub outline: Option<([f32; 4], f32)>,
+}
+
+impl StateConfig {
+    // The text with the default camera and every effect off
+    pub fn new(text: &str) -> Self {
+        StateConfig {
+            text: text.to_string(),
+            layout: LayoutConfig::default(),
+            camera_eye: [0.0, 0.0, 7.0],
+            camera_target: [0.0, 0.0, 0.0],
+            projection: Projection::Perspective { fovy: 45.0 },
+            displacement_focus: InitialDisplacement::default().focus,
+            displacement_strength: InitialDisplacement::default().strength,
+            gradient: None,
+            decoration: TextDecoration::default(),
+            marquee_speed: 0.0,
+            debug_colors: false,
+            show_bounds: false,
+            anti_aliasing: AntiAliasing::None,
+            clear_color: Settings::default().clear_color,
+            drop_shadow: None,
+            outline: None,
+        }
+    }
+}
+
+// What's kept between runs of the demo, saved with platform_specific::save_settings. Fields missing from
+// older saved settings keep their defaults
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct Settings {
+    camera_eye: [f32; 3],
+    camera_target: [f32; 3],
+    projection: Projection,
+    // rgba
+    clear_color: [f64; 4],
+    displacement_mode: DisplacementMode,
+    focus_half_life: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            camera_eye: [0.0, 0.0, 7.0],
+            camera_target: [0.0, 0.0, 0.0],
+            projection: Projection::Perspective { fovy: 45.0 },
+            clear_color: [1.0, 1.0, 1.0, 0.0],
+            displacement_mode: DisplacementMode::default(),
+            focus_half_life: DEFAULT_FOCUS_HALF_LIFE,
+        }
+    }
+}
+
+// A named demo configuration, applied with State::apply_scene
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub name: String,
+    pub config: StateConfig,
+}
+
+// The scenes App cycles through with the tab key, starting with the initial text
+fn demo_scenes(text: &str) -> Vec<Scene> {
+    let scene = |name: &str, config| Scene { name: name.to_string(), config };
+    vec![
+        scene("default", StateConfig::new(text)),
+        scene("sunset", StateConfig {
+            gradient: Some(([1.0, 0.6, 0.2, 1.0], [0.6, 0.1, 0.5, 1.0])),
+            decoration: TextDecoration { underline: true, ..Default::default() },
+            anti_aliasing: AntiAliasing::Msaa(4),
+            ..StateConfig::new(text)
+        }),
+        scene("heading", StateConfig {
+            layout: LayoutConfig {
+                color_gradient: Some(TextGradient {
+                    start: [0.2, 0.5, 1.0, 1.0],
+                    end: [1.0, 0.3, 0.6, 1.0],
+                    axis: GradientAxis::Horizontal,
+                }),
+                ..Default::default()
+            },
+            ..StateConfig::new(text)
+        }),
+        scene("marquee", StateConfig {
+            camera_eye: [0.0, -2.0, 9.0],
+            projection: Projection::Perspective { fovy: 35.0 },
+            marquee_speed: 3.0,
+            ..StateConfig::new(text)
+        }),
+        scene("debug", StateConfig {
+            layout: LayoutConfig { alignment: Alignment::Justify, advance: 1.5, ..Default::default() },
+            debug_colors: true,
+            show_bounds: true,
+            ..StateConfig::new(text)
+        }),
+    ]
+}
+
+#[derive(Debug, Clone)]
+struct VertexData {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Model {
+    instances: Vec<Instance>,
+    instance_buffer: wgpu::Buffer,
+    vertex_data: VertexData,
+    // Replaces the shared letter texture for this model when set
+    texture: Option<ModelTexture>,
+}
+
+#[derive(Debug, Clone)]
+struct ModelTexture {
+    texture: texture::GpuTexture,
+    bind_group: wgpu::BindGroup,
+}
+
+#[derive(Debug, Clone)]
+pub struct Instance {
+    position: cgmath::Vector3<f32>,
+    rotation: cgmath::Quaternion<f32>,
+    scale: f32,
+    // Multiplied with the letter's color, white leaves it unchanged
+    color: [f32; 4],
+}
+
+impl Instance {
+    fn to_raw(&self) -> InstanceRaw {
+        InstanceRaw {
+            model: ( cgmath::Matrix4::from_translation(self.position) * cgmath::Matrix4::from(self.rotation) * cgmath::Matrix4::from_scale(self.scale) ).into(),
+            color: self.color,
+        }
+    }
+}
+
+// Float fields of two instances closer than this are considered equal
+const INSTANCE_EPSILON: f32 = 1e-5;
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        let close = |a: f32, b: f32| (a - b).abs() <= INSTANCE_EPSILON;
+        let rotation_close = |sign: f32| {
+            close(self.rotation.s, sign * other.rotation.s)
+                && (0..3).all(|i| close(self.rotation.v[i], sign * other.rotation.v[i]))
+        };
+        (0..3).all(|i| close(self.position[i], other.position[i]))
+            // q and -q are the same rotation
+            && (rotation_close(1.0) || rotation_close(-1.0))
+            && close(self.scale, other.scale)
+            && (0..4).all(|i| close(self.color[i], other.color[i]))
+    }
+}
+
+// Which buffers the letters are drawn from. Both draw the same vertices and instances, so the output is identical
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RenderBackend {
+    // Each letter model is drawn from its own vertex, index and instance buffers
+    Instanced,
+    // Every letter model is packed into one vertex, index and instance buffer that's bound once per pass.
+    // Where the device has INDIRECT_FEATURES the letters are drawn with one indirect call per texture
+    Merged,
+}
+
+// Counts of what was recorded for the last frame, to compare the render backends
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub triangles: u32,
+    pub buffer_binds: u32,
+}
+
+impl RenderStats {
+    fn record_draw(&mut self, num_indices: u32, instances: &std::ops::Range<u32>) {
+        self.draw_calls += 1;
+        self.triangles += num_indices / 3 * instances.len() as u32;
+    }
+
+    // Several draws issued by one multi_draw_indexed_indirect call
+    fn record_multi_draw<'a>(&mut self, draws: impl Iterator<Item = (u32, &'a std::ops::Range<u32>)>) {
+        self.draw_calls += 1;
+        for (num_indices, instances) in draws {
+            self.triangles += num_indices / 3 * instances.len() as u32;
+        }
+    }
+}
+
+// Both are needed to draw the merged models with one multi_draw_indexed_indirect call. Without them, like on
+// WebGL2, the merged models are drawn with a draw_indexed call per model
+const INDIRECT_FEATURES: wgpu::Features = wgpu::Features::MULTI_DRAW_INDIRECT.union(wgpu::Features::INDIRECT_FIRST_INSTANCE);
+const INDIRECT_ARGS_SIZE: u64 = std::mem::size_of::<wgpu::util::DrawIndexedIndirectArgs>() as u64;
+
+// The letter models packed into shared buffers, for RenderBackend::Merged
+#[derive(Debug, Clone)]
+struct MergedModels {
+    vertex_buffer: wgpu::Buffer,
+    // u32 indices, already offset to each model's first vertex
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    // The first index and first instance of each model
+    offsets: Vec<(u32, u32)>,
+    // The args of each letter draw, rewritten every frame by write_indirect_draws. Fits a draw per instance, the
+    // most draws the instances can be split into. None when the device lacks INDIRECT_FEATURES
+    indirect_buffer: Option<wgpu::Buffer>,
+}
+
+impl MergedModels {
+    // Writes the indirect args record_draws draws letter_draws with, in the same order. Does nothing without
+    // an indirect_buffer
+    fn write_indirect_draws(&self, queue: &wgpu::Queue, models: &[Model], letter_draws: &[(usize, std::ops::Range<u32>)]) {
+        let Some(indirect_buffer) = &self.indirect_buffer else { return };
+        let args: Vec<u8> = letter_draws.iter().filter(|(_, instances)| !instances.is_empty()).flat_map(|(i, instances)| {
+            let (first_index, first_instance) = self.offsets[*i];
+            wgpu::util::DrawIndexedIndirectArgs {
+                index_count: models[*i].vertex_data.num_indices,
+                instance_count: instances.len() as u32,
+                first_index,
+                base_vertex: 0,
+                first_instance: first_instance + instances.start,
+            }.as_bytes().to_vec()
+        }).collect();
+        if !args.is_empty() && args.len() as u64 <= indirect_buffer.size() {
+            queue.write_buffer(indirect_buffer, 0, &args);
+        }
+    }
+}
+
+// Packs the letter models into shared buffers. models[i] must have been created from alphabet_models[i]
+fn create_merged_models(device: &wgpu::Device, models: &[Model], alphabet_models: &[letters::Model]) -> MergedModels {
+    let mut verts = vec![];
+    let mut indices: Vec<u32> = vec![];
+    let mut instances: Vec<InstanceRaw> = vec![];
+    let mut offsets = Vec::with_capacity(models.len());
+    for (model, mesh) in models.iter().zip(alphabet_models) {
+        offsets.push((indices.len() as u32, instances.len() as u32));
+        let base_vertex = verts.len() as u32;
+        indices.extend(mesh.tri_idxs.iter().flatten().map(|&i| base_vertex + i as u32));
+        verts.extend_from_slice(&mesh.verts);
+        instances.extend(model.instances.iter().map(Instance::to_raw));
+    }
+    let indirect_buffer = device.features().contains(INDIRECT_FEATURES).then(|| device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("merged_indirect_buffer"),
+        size: instances.len().max(1) as u64 * INDIRECT_ARGS_SIZE,
+        usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    }));
+    MergedModels {
+        vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("merged_vertex_buffer"),
+            contents: bytemuck::cast_slice(&verts),
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+        index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("merged_index_buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        }),
+        instance_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("merged_instance_buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+        offsets,
+        indirect_buffer,
+    }
+}
+
+// The fraction of the remaining distance the displacement focus moves in dt seconds
+fn focus_smoothing(dt: f32, half_life: f32) -> f32 {
+    if half_life <= 0.0 {
+        1.0
+    } else {
+        1.0 - f32::exp2(-dt / half_life)
+    }
+}
+
+// The displacement strength dt seconds later, growing up to max while the cursor is on the window
+// and decaying otherwise. Stepping twice by dt / 2 gives the same result as once by dt
+fn step_displacement_strength(strength: f32, dt: f32, cursor_on_window: bool, max: f32) -> f32 {
+    if cursor_on_window {
+        let grown = (strength + STRENGTH_GROWTH_OFFSET) * f32::exp2(dt / STRENGTH_GROWTH_DOUBLING) - STRENGTH_GROWTH_OFFSET;
+        grown.clamp(0.0, max)
+    } else {
+        strength * f32::exp2(-dt / STRENGTH_DECAY_HALF_LIFE)
+    }
+}
+
+// The buffers record_draws draws the letters from
+#[derive(Clone, Copy)]
+enum LetterBuffers<'a> {
+    // Each model's own vertex, index and instance buffers
+    Models,
+    Merged(&'a MergedModels),
+    // Each model's own vertex and index buffers, with the instances the compute pass moved
+    Displaced(&'a displace::ComputeDisplacement),
+}
+
+// Records the draws of the letters and then the overlays.
+// Bind group 0 is switched to a model's own texture when it has one
+fn record_draws(
+    renderpass: &mut wgpu::RenderPass,
+    models: &[Model],
+    letter_draws: &[(usize, std::ops::Range<u32>)],
+    letter_buffers: LetterBuffers,
+    overlays: &[&Model],
+    texture_bind_group: &wgpu::BindGroup,
+    stats: &mut RenderStats,
+) {
+    match letter_buffers {
+        LetterBuffers::Merged(merged) => {
+            renderpass.set_vertex_buffer(0, merged.vertex_buffer.slice(..));
+            renderpass.set_vertex_buffer(1, merged.instance_buffer.slice(..));
+            renderpass.set_index_buffer(merged.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            stats.buffer_binds += 3;
+            let draws: Vec<&(usize, std::ops::Range<u32>)> = letter_draws.iter().filter(|(_, instances)| !instances.is_empty()).collect();
+            let bind_group = |i: usize| model_texture_bind_group(&models[i], texture_bind_group);
+            match &merged.indirect_buffer {
+                // One call per run of draws sharing a texture, from the args written by write_indirect_draws
+                Some(indirect_buffer) if draws.len() as u64 * INDIRECT_ARGS_SIZE <= indirect_buffer.size() => {
+                    let mut first = 0;
+                    for run in draws.chunk_by(|a, b| std::ptr::eq(bind_group(a.0), bind_group(b.0))) {
+                        renderpass.set_bind_group(0, bind_group(run[0].0), &[]);
+                        renderpass.multi_draw_indexed_indirect(indirect_buffer, first as u64 * INDIRECT_ARGS_SIZE, run.len() as u32);
+                        stats.record_multi_draw(run.iter().map(|(i, instances)| (models[*i].vertex_data.num_indices, instances)));
+                        first += run.len();
+                    }
+                }
+                _ => {
+                    for (i, instances) in draws {
+                        let model = &models[*i];
+                        let (first_index, first_instance) = merged.offsets[*i];
+                        renderpass.set_bind_group(0, bind_group(*i), &[]);
+                        renderpass.draw_indexed(
+                            first_index..first_index + model.vertex_data.num_indices,
+                            0,
+                            first_instance + instances.start..first_instance + instances.end,
+                        );
+                        stats.record_draw(model.vertex_data.num_indices, instances);
+                    }
+                }
+            }
+        }
+        LetterBuffers::Models | LetterBuffers::Displaced(_) => {
+            for (i, instances) in letter_draws {
+                let displaced = match letter_buffers {
+                    LetterBuffers::Displaced(displaced) => displaced.output(*i),
+                    _ => None,
+                };
+                let instance_buffer = displaced.unwrap_or(&models[*i].instance_buffer);
+                record_instanced_draw(renderpass, &models[*i], instance_buffer, instances.clone(), texture_bind_group, stats);
+            }
+        }
+    }
+    for model in overlays {
+        record_instanced_draw(renderpass, model, &model.instance_buffer, 0..model.instances.len() as u32, texture_bind_group, stats);
+    }
+}
+
+// Draws a model from its own buffers
+fn record_instanced_draw(
+    renderpass: &mut wgpu::RenderPass,
+    model: &Model,
+    instance_buffer: &wgpu::Buffer,
+    instances: std::ops::Range<u32>,
+    texture_bind_group: &wgpu::BindGroup,
+    stats: &mut RenderStats,
+) {
+    if !instances.is_empty() {
+        renderpass.set_bind_group(0, model_texture_bind_group(model, texture_bind_group), &[]);
+        renderpass.set_vertex_buffer(0, model.vertex_data.vertex_buffer.slice(..));
+        renderpass.set_vertex_buffer(1, instance_buffer.slice(..));
+        renderpass.set_index_buffer(model.vertex_data.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        stats.buffer_binds += 3;
+
+        stats.record_draw(model.vertex_data.num_indices, &instances);
+        renderpass.draw_indexed(0..model.vertex_data.num_indices, 0, instances);
+    }
+}
+
+// A model's own texture, bound with the shared normal map in place of the letter texture
+fn create_model_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    rgba: &texture::RgbaTexture<[u8; 4]>,
+    normal_texture: &texture::GpuTexture,
+    letter_index: usize,
+) -> ModelTexture {
+    let label = "letter_texture index: ".to_string() + &letter_index.to_string();
+    let texture = texture::GpuTexture::from_rgbatexture(rgba, device, queue, &label, false, true, texture::SamplerConfig::default());
+    let bind_group = create_texture_bind_group(device, layout, &texture, normal_texture);
+    ModelTexture { texture, bind_group }
+}
+
+// The model's own texture, or the shared letter texture
+fn model_texture_bind_group<'a>(model: &'a Model, texture_bind_group: &'a wgpu::BindGroup) -> &'a wgpu::BindGroup {
+    model.texture.as_ref().map_or(texture_bind_group, |t| &t.bind_group)
+}
+
+// Orders every instance of the models from the farthest from eye to the nearest. Returns the model index
+// and instance range of each draw, consecutive instances of the same model are merged into one draw
+fn back_to_front_draws(models: &[Model], eye: cgmath::Point3<f32>) -> Vec<(usize, std::ops::Range<u32>)> {
+    let mut order: Vec<(usize, u32, f32)> = models.iter().enumerate().flat_map(|(i, model)| {
+        model.instances.iter().enumerate().map(move |(j, instance)| {
+            (i, j as u32, (cgmath::Point3::from_vec(instance.position) - eye).magnitude2())
+        })
+    }).collect();
+    order.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut draws: Vec<(usize, std::ops::Range<u32>)> = vec![];
+    for (i, j, _) in order {
+        match draws.last_mut() {
+            Some((last, range)) if *last == i && range.end == j => range.end += 1,
+            _ => draws.push((i, j..j + 1)),
+        }
+    }
+    draws
+}
+
+// The most the idle wave in vs_main moves a letter, the length of its (0.3, 0.3, 0.2) amplitude
+const CULL_WAVE_MARGIN: f32 = 0.48;
+// The most a focus point with a strength of 1.0 moves a letter in any displacement mode, the length of the
+// radial push's (3.0, 2.0) xy and z reach. The other modes move letters less
+const CULL_FOCUS_MARGIN: f32 = 3.61;
+// The glyphs are scaled around this point for the outline, matches GLYPH_CENTER in shader.wgsl
+const GLYPH_CENTER: [f32; 3] = [0.0, 0.5, 0.0];
+
+// The planes bounding what view_proj shows, as (normal, distance) with the normals pointing inward and normalized.
+// A point p is inside when dot(normal, p) + distance >= 0 for every plane
+fn frustum_planes(view_proj: cgmath::Matrix4<f32>) -> [cgmath::Vector4<f32>; 6] {
+    let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+    // -w <= x <= w and -w <= y <= w, wgpu's depth is 0 <= z <= w
+    [w + x, w - x, w + y, w - y, z, w - z].map(|plane| plane / plane.truncate().magnitude())
+}
+
+// Splits each draw into the runs of its instances whose bounding sphere reaches into the frustum, dropping
+// the rest. margin is added to every sphere's radius for how far the shaders move letters from their instance.
+// outline_scale is how much the outline pipeline scales the glyphs up, 1.0 without an outline.
+// Models without bounds have nothing to draw and are dropped entirely
+fn cull_draws(
+    draws: Vec<(usize, std::ops::Range<u32>)>,
+    models: &[Model],
+    glyph_bounds: &[Option<letters::Bounds>],
+    view_proj: cgmath::Matrix4<f32>,
+    margin: f32,
+    outline_scale: f32,
+) -> Vec<(usize, std::ops::Range<u32>)> {
+    let planes = frustum_planes(view_proj);
+    // Each glyph's bounding sphere in its own space, as (center, radius)
+    let spheres: Vec<Option<(cgmath::Vector3<f32>, f32)>> = glyph_bounds.iter().map(|bounds| bounds.map(|b| {
+        let (min, max) = (cgmath::Vector3::from(b.min), cgmath::Vector3::from(b.max));
+        let center = 0.5 * (min + max);
+        let radius = 0.5 * (max - min).magnitude();
+        // The outline moves each point of the glyph away from GLYPH_CENTER by (outline_scale - 1) times its distance
+        let outline_reach = (outline_scale - 1.0).max(0.0) * (radius + (center - cgmath::Vector3::from(GLYPH_CENTER)).magnitude());
+        (center, radius + outline_reach)
+    })).collect();
+
+    let mut culled: Vec<(usize, std::ops::Range<u32>)> = vec![];
+    for (i, instances) in draws {
+        let Some((center, radius)) = spheres[i] else { continue };
+        for j in instances {
+            let instance = &models[i].instances[j as usize];
+            let world_center = instance.position + instance.rotation * (instance.scale * center);
+            let world_radius = instance.scale * radius + margin;
+            let visible = planes.iter().all(|plane| plane.truncate().dot(world_center) + plane.w >= -world_radius);
+            if !visible {
+                continue;
+            }
+            match culled.last_mut() {
+                Some((last, range)) if *last == i && range.end == j => range.end += 1,
+                _ => culled.push((i, j..j + 1)),
+            }
+        }
+    }
+    culled
+}
+
+// Removes instances equal to an earlier instance, keeping the order of the rest
+fn dedup_instances(instances: Vec<Instance>) -> Vec<Instance> {
+    let mut unique: Vec<Instance> = Vec::with_capacity(instances.len());
+    for instance in instances {
+        if !unique.contains(&instance) {
+            unique.push(instance);
+        }
+    }
+    unique
+}
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// A distinct color for each letter, to see where each glyph's geometry lands
+fn debug_color(letter_index: usize) -> [f32; 4] {
+    // Stepping the hue by the golden ratio keeps neighboring letters far apart on the color wheel
+    let hue = (letter_index as f32 * 0.618_034).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let [r, g, b] = match hue as u32 {
+        0 => [1.0, x, 0.0],
+        1 => [x, 1.0, 0.0],
+        2 => [0.0, 1.0, x],
+        3 => [0.0, x, 1.0],
+        4 => [x, 0.0, 1.0],
+        _ => [1.0, 0.0, x],
+    };
+    [r, g, b, 1.0]
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+impl InstanceRaw {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4, 9 => Float32x4];
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            // Steps on each change of the instance, not the vertex
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBS
+        }
+    }
+}
+
+// World-space extents of a block of text, as laid out by get_letter_instances
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextMetrics {
+    pub width: f32,
+    pub height: f32,
+    pub line_count: usize,
+    // World y of the first line's baseline (the bottom of its glyphs)
+    pub baseline: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightUniform {
+    position: [f32; 3],
+    _padding: u32, // Padding so struct+struct fields are 16 byte aligned for web
+    color: [f32; 3],
+    // Non-zero when the letter normal texture perturbs the lighting normal, fills color's padding
+    normal_map: u32,
+}
+
+impl LightUniform {
+    fn new(position: [f32; 3], color: [f32; 3]) -> Self {
+        LightUniform {
+            position,
+            _padding: 0,
+            color,
+            normal_map: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GradientUniform {
+    top: [f32; 4],
+    bottom: [f32; 4],
+    // Non-zero when the gradient replaces the letter texture color
+    enabled: u32,
+    _padding: [u32; 3],
+}
+
+impl GradientUniform {
+    fn new(top: [f32; 4], bottom: [f32; 4], enabled: bool) -> Self {
+        GradientUniform {
+            top,
+            bottom,
+            enabled: enabled as u32,
+            _padding: [0; 3],
+        }
+    }
+}
+
+// The outline drawn behind the letters, see vs_outline in shader.wgsl
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct OutlineUniform {
+    color: [f32; 4],
+    // How far the outline reaches past each side of a letter, in units of the letter's size
+    width: f32,
+    _padding: [f32; 3],
+}
+
+impl OutlineUniform {
+    fn new(color: [f32; 4], width: f32) -> Self {
+        OutlineUniform {
+            color,
+            width,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+// The most focus points displacement_buffer holds, kept small for WebGL2's uniform limits.
+// Must match the array length of FocusPoints in shader.wgsl and displace.wgsl
+const MAX_FOCUS_POINTS: usize = 8;
+
+// A point the letters are displaced around. The position is in normalized device coordinates like cursor_pos
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FocusPoint {
+    pub position: [f32; 2],
+    pub strength: f32,
+}
+
+// The contents of displacement_buffer. The layout entry's min_binding_size is the size of this struct, so
+// wgpu rejects the pipeline if it doesn't match the shader's FocusPoints
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DisplacementUniform {
+    // xyz is a point's world position and w its strength, only the first count are used
+    points: [[f32; 4]; MAX_FOCUS_POINTS],
+    count: u32,
+    _padding: [u32; 3],
+}
+
+impl DisplacementUniform {
+    // Points past MAX_FOCUS_POINTS are dropped
+    fn new(points: &[[f32; 4]]) -> Self {
+        let mut uniform = DisplacementUniform {
+            points: [[0.0; 4]; MAX_FOCUS_POINTS],
+            count: points.len().min(MAX_FOCUS_POINTS) as u32,
+            _padding: [0; 3],
+        };
+        for (slot, point) in uniform.points.iter_mut().zip(points) {
+            *slot = *point;
+        }
+        uniform
+    }
+
+    // The single focus point the buffer starts with, the initial focus unprojected onto the text's z plane
+    fn initial(initial: InitialDisplacement, z_plane: f32, inverse_view_proj: cgmath::Matrix4<f32>) -> Self {
+        let position = Camera::find_3d_mouse_pos(initial.focus, z_plane, inverse_view_proj);
+        Self::new(&[[position.x, position.y, position.z, initial.strength]])
+    }
+}
+
+#[derive(Clone)]
+pub struct Camera {
+    eye: cgmath::Point3<f32>,
+    target: cgmath::Point3<f32>,
+    up: cgmath::Vector3<f32>,
+    aspect: f32,
+    projection: Projection,
+    znear: f32,
+    zfar: f32,
+    // Cached look_at matrix, only the projection changes with projection and aspect
+    view: cgmath::Matrix4<f32>,
+    // Set when the camera changed and the uniform needs to be rewritten
+    dirty: bool,
+}
+
+impl Camera {
+    pub fn new_default(aspect_ratio: f32) -> Self {
+        let mut camera = Camera {
+            eye: (0.0, 0.0, 7.0).into(),
+            // get_letter_instances centers the text on the origin
+            target: (0.0, 0.0, 0.0).into(),
+            up: cgmath::Vector3::unit_y(),
+            aspect: aspect_ratio,
+            projection: Projection::Perspective { fovy: 45.0 },
+            znear: 0.1,
+            zfar: 100.0,
+            view: cgmath::Matrix4::identity(),
+            dirty: true,
+        };
+        camera.update_view();
+        camera
+    }
+
+    // Recomputes the cached view matrix, needed after eye, target or up change
+    fn update_view(&mut self) {
+        self.view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
+        self.dirty = true;
+    }
+
+    // Rotates the eye around the target, keeping its distance. Azimuth turns around the y axis and
+    // elevation tilts over it, stopping short of the poles where the view would flip over
+    pub fn orbit(&mut self, d_azimuth: f32, d_elevation: f32) {
+        const MAX_ELEVATION: f32 = 85.0 * std::f32::consts::PI / 180.0;
+        let offset = self.eye - self.target;
+        let radius = offset.magnitude();
+        if radius == 0.0 {
+            return;
+        }
+        let azimuth = offset.x.atan2(offset.z) + d_azimuth;
+        let elevation = ((offset.y / radius).asin() + d_elevation).clamp(-MAX_ELEVATION, MAX_ELEVATION);
+        let horizontal = radius * elevation.cos();
+        self.eye = self.target + cgmath::Vector3::new(horizontal * azimuth.sin(), radius * elevation.sin(), horizontal * azimuth.cos());
+        self.update_view();
+    }
+
+    // Moves the eye toward the target for factor < 1.0 and away for factor > 1.0, along the view direction.
+    // The distance stays within [MIN_ZOOM_RADIUS, MAX_ZOOM_RADIUS]
+    pub fn zoom(&mut self, factor: f32) {
+        let offset = self.eye - self.target;
+        let radius = offset.magnitude();
+        if radius == 0.0 {
+            return;
+        }
+        let new_radius = (radius * factor).clamp(MIN_ZOOM_RADIUS, MAX_ZOOM_RADIUS);
+        self.eye = self.target + offset * (new_radius / radius);
+        // Moving the eye doesn't change the size of an orthographic view, shrink the view with it
+        if let Projection::Orthographic { height } = &mut self.projection {
+            *height *= new_radius / radius;
+        }
+        self.update_view();
+    }
+
+    // Only touches the projection, the view matrix is kept as is
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.dirty = true;
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+        self.dirty = true;
+    }
+
+    fn build_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        match self.projection {
+            Projection::Perspective { fovy } => cgmath::perspective(cgmath::Deg(fovy), self.aspect, self.znear, self.zfar),
+            Projection::Orthographic { height } => {
+                let (half_width, half_height) = (0.5 * height * self.aspect, 0.5 * height);
+                cgmath::ortho(-half_width, half_width, -half_height, half_height, self.znear, self.zfar)
+            }
+        }
+    }
+
+    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        OPENGL_TO_WGPU_MATRIX * self.build_projection_matrix() * self.view
+    }
+
+    fn create_matrices(&self) -> (CameraUniform, cgmath::Matrix4<f32>) {
+        let view_proj = self.build_view_projection_matrix();
+        let uniform = CameraUniform {
+            view_pos: self.eye.to_homogeneous().into(),
+            view_proj: view_proj.into(),
+        };
+        (uniform, view_proj.invert().unwrap_or(ZERO_MATRIX))
+    }
+
+    // Unprojects a point in normalized device coordinates into a world-space ray, returned as (origin, direction).
+    // The origin lies on the near plane and the direction points away from the camera
+    pub fn ray_from_screen(&self, x_ndc: f32, y_ndc: f32) -> (cgmath::Point3<f32>, cgmath::Vector3<f32>) {
+        let inverse_view_proj = self.build_view_projection_matrix().invert().unwrap_or(ZERO_MATRIX);
+        Self::unproject_ray([x_ndc, y_ndc], inverse_view_proj)
+    }
+
+    fn unproject_ray(
+        screen_position: [f32; 2],
+        inverse_view_proj: cgmath::Matrix4<f32>
+    ) -> (cgmath::Point3<f32>, cgmath::Vector3<f32>) {
+        // Invert the view to find the respective positions at znear and zfar (wgpu depth goes from 0 to 1)
+        let near_clip = inverse_view_proj * cgmath::Vector4::new(screen_position[0], screen_position[1], 0.0, 1.0);
+        let near = near_clip.div_element_wise(near_clip.w).truncate();
+        let far_clip = inverse_view_proj * cgmath::Vector4::new(screen_position[0], screen_position[1], 1.0, 1.0);
+        let far = far_clip.div_element_wise(far_clip.w).truncate();
+        (cgmath::Point3::from_vec(near), (far - near).normalize())
+    }
+
+    fn find_3d_mouse_pos(
+        mouse_position: [f32; 2],
+        target_z_value: f32,
+        inverse_view_proj: cgmath::Matrix4<f32>
+    ) -> cgmath::Vector3<f32> {
+        // Find where the ray under the cursor intersects with the target z plane.
+        let (origin, direction) = Self::unproject_ray(mouse_position, inverse_view_proj);
+        let mult = (target_z_value - origin.z) / direction.z;
+        origin.to_vec() + mult * direction
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniform {
+    view_pos: [f32; 4],
+    view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    // This page intentionally left blank
+}
+
+// Maps OpenGL's clip space z of [-w, w] to wgpu's [0, w]. Matrix4::new takes the columns, so the 0.5 offset
+// is the last column's
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+#[rustfmt::skip]
+const ZERO_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
+    0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0,
+);
+
+// The device, pipeline, glyph buffers and textures are wgpu handles that can be cloned to share them
+// between windows, see State::share
+pub struct Gpu {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    // None without a window
+    surface: Option<wgpu::Surface<'static>>,
+    surface_configured: bool,
+    // Set when the last acquired frame was suboptimal, the surface is reconfigured before the next frame
+    surface_suboptimal: bool,
+    // Frames in a row the surface texture couldn't be acquired, see State::handle_surface_error
+    surface_errors: u32,
+    surface_format: wgpu::TextureFormat,
+    render_pipeline: wgpu::RenderPipeline,
+    // Draws the scaled up letters behind the letters, None when the outline is disabled
+    outline_pipeline: Option<wgpu::RenderPipeline>,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    pipeline_config: PipelineConfig,
+    // Temporal anti-aliasing, None when disabled
+    taa: Option<taa::Taa>,
+    // Offscreen target the scene is rendered into when render_scale isn't 1.0
+    supersample: Option<supersample::Supersample>,
+    // Multisampled color target, resolved into the scene target. None when pipeline_config.sample_count is 1
+    msaa_target: Option<wgpu::TextureView>,
+    // Depth attachment matching the scene target, None when pipeline_config.depth_format is None
+    depth_target: Option<wgpu::TextureView>,
+    // Drop shadow drawn under the letters, None when disabled
+    shadow: Option<shadow::Shadow>,
+    // Displaces the letter instances in a compute pass instead of the vertex shader, None when disabled
+    compute_displacement: Option<displace::ComputeDisplacement>,
+    // Times the main pass on the gpu, None when the device has no timestamp queries
+    timer: Option<gpu_timer::GpuTimer>,
+    // What the adapter supports for the format the scene is rendered in, used to validate sample counts
+    format_features: wgpu::TextureFormatFeatureFlags,
+    adapter_features: wgpu::Features,
+    adapter_info: wgpu::AdapterInfo,
+    models: [Model; letters::GLYPH_COUNT],
+    // The models packed into shared buffers, Some only while State::render_backend is Merged
+    merged: Option<MergedModels>,
+    // Underline/strikethrough geometry for the current text, None when there's nothing to draw
+    decoration_model: Option<Model>,
+    // Outlines around each letter instance, Some only while State::show_bounds is set
+    bounds_model: Option<Model>,
+    universal_bind_groups: Vec<wgpu::BindGroup>,
+    // Kept to build per-model texture bind groups, and per-window camera and misc bind groups
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    misc_bind_group_layout: wgpu::BindGroupLayout,
+    // The shared letter texture and the normal texture every texture bind group samples
+    letter_texture: texture::GpuTexture,
+    letter_normal_texture: texture::GpuTexture,
+}
+
+pub struct State {
+    // None for a headless state, see State::new_headless
+    window: Option<Arc<Window>>,
+    // The surface's size in physical pixels, the window's logical size times its scale factor
+    size: winit::dpi::PhysicalSize<u32>,
+    scale_factor: f64,
+    gpu: Gpu,
+    decoration: TextDecoration,
+    // (x, y, width, height) in physical pixels, letters outside of it are clipped
+    scissor: Option<(u32, u32, u32, u32)>,
+    // The resolution the scene is rendered at relative to the surface, downsampled when presenting
+    render_scale: f32,
+    anti_aliasing: AntiAliasing,
+    // The radius of the drop shadow's blur in pixels
+    shadow_blur: f32,
+    // What the scene is cleared to. A transparent clear lets the page show through the canvas on wasm
+    clear_color: wgpu::Color,
+    layout: LayoutConfig,
+    // The text currently laid out into the models, and the letter geometry to rebuild them from
+    text: String,
+    alphabet_models: Vec<letters::Model>,
+    // Each letter is tinted with debug_color
+    debug_colors: bool,
+    show_bounds: bool,
+    glyph_map: GlyphMap,
+    // Draw the letter instances back to front instead of grouped by letter, so blended letters overlap correctly
+    sort_instances: bool,
+    // Skip the letter instances outside the camera's view, see cull_draws
+    frustum_culling: bool,
+    render_backend: RenderBackend,
+    render_stats: RenderStats,
+    glyph_bounds: [Option<letters::Bounds>; letters::GLYPH_COUNT],
+
+    start_time: web_time::Instant,
+    // While paused the time, displacement and scroll stop advancing but frames are still presented
+    paused: bool,
+    paused_at: web_time::Instant,
+    // Total time spent paused, taken off the animation time so it resumes where it stopped
+    paused_duration: web_time::Duration,
+    time_buffer: wgpu::Buffer,
+    size_buffer: wgpu::Buffer,
+
+    cursor_clicked: bool,
+    cursor_pos: [f32; 2],
+    cursor_on_window: bool,
+    touch_id: u64,
+    // The modifier keys currently held, keys pressed with ctrl/alt/super are shortcuts rather than text
+    modifiers: winit::keyboard::ModifiersState,
+
+    camera: Camera,
+    camera_uniform: CameraUniform,
+    inverse_camera_mat: cgmath::Matrix4<f32>,
+    camera_buffer: wgpu::Buffer,
+
+    light_uniform: LightUniform,
+    light_buffer: wgpu::Buffer,
+
+    // The letters are displaced around every point. The first follows the cursor, the rest are placed with
+    // set_extra_focus_points
+    focus_points: Vec<FocusPoint>,
+    // How the first focus point follows the cursor, see DEFAULT_FOCUS_HALF_LIFE
+    focus_half_life: f32,
+    displacement_buffer: wgpu::Buffer,
+
+    gradient_uniform: GradientUniform,
+    gradient_buffer: wgpu::Buffer,
+
+    outline_uniform: OutlineUniform,
+    outline_buffer: wgpu::Buffer,
+
+    // World units per second the text scrolls left, 0.0 when the marquee is off
+    marquee_speed: f32,
+    scroll_offset: f32,
+    scroll_buffer: wgpu::Buffer,
+    displacement_mode: DisplacementMode,
+    displacement_mode_buffer: wgpu::Buffer,
+    last_frame_time: web_time::Instant,
+    // Exponential moving average of the seconds between frames, see frame_time
+    average_frame_time: f32,
+    frame_rate_shown_at: web_time::Instant,
+    // The settings as last saved or loaded, they're saved again once they differ
+    saved_settings: Settings,
+    settings_checked_at: web_time::Instant,
+}
+
+impl State {
+    // Fails when the platform has no gpu, or no backend, that can draw to the window
+    pub async fn new(window: Arc<Window>, init_content: Arc<InitContent>) -> Result<State, GpuError> {
+
+        // Handle wgpu portion of State creation:
+        let instance_descriptor = platform_specific::instance_descriptor();
+        let instance = wgpu::Instance::new(&instance_descriptor);
+
+        let surface = instance.create_surface(window.clone()).map_err(GpuError::Surface)?;
+        let adapter_options = wgpu::RequestAdapterOptions {
+            compatible_surface: Some(&surface),
+            ..Default::default()
+        };
+
+        let adapter = instance
+            .request_adapter(&adapter_options)
+            .await
+            .ok_or(GpuError::NoAdapter)?;
+
+        let size = window.inner_size(); //This is zero on wasm during init and causes errors
+                                        //if you configure the surface with a size of zero
+        let window_scale_factor = window.scale_factor();
+
+        let cap = surface.get_capabilities(&adapter);
+        let surface_format = cap.formats.iter()
+            .find(|f| f.is_srgb())
+            .copied()
+            .unwrap_or(cap.formats[0]);
+
+        let mut state = State::with_adapter(instance, adapter, Some((window, surface)), size, surface_format, &init_content).await?;
+        state.scale_factor = window_scale_factor;
+        // Restore what the last run saved, or keep the defaults
+        if let Some(settings) = platform_specific::load_settings::<Settings>() {
+            state.apply_settings(&settings);
+            state.saved_settings = settings;
+        }
+        Ok(state)
+    }
+
+    // A state without a window, that only draws with render_to_image. Its scene is rendered as Rgba8UnormSrgb
+    pub async fn new_headless(size: winit::dpi::PhysicalSize<u32>, init_content: Arc<InitContent>) -> Result<State, GpuError> {
+        let instance = wgpu::Instance::new(&platform_specific::instance_descriptor());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .ok_or(GpuError::NoAdapter)?;
+        State::with_adapter(instance, adapter, None, size, wgpu::TextureFormat::Rgba8UnormSrgb, &init_content).await
+    }
+
+    // Everything but the surface, which is None for a headless state
+    async fn with_adapter(
+        instance: wgpu::Instance,
+        adapter: wgpu::Adapter,
+        window_surface: Option<(Arc<Window>, wgpu::Surface<'static>)>,
+        size: winit::dpi::PhysicalSize<u32>,
+        surface_format: wgpu::TextureFormat,
+        init_content: &InitContent,
+    ) -> Result<State, GpuError> {
+        let adapter_info = adapter.get_info();
+        platform_specific::print(&format!(
+            "Using {} ({:?} backend), optional features: {:?}",
+            adapter_info.name,
+            adapter_info.backend,
+            adapter.features(),
+        ));
+
+        let device_descriptor = platform_specific::device_descriptor(adapter.features());
+        let (device, queue) = adapter
+            .request_device(&device_descriptor, None)
+            .await
+            .map_err(GpuError::Device)?;
+        let (window, surface) = window_surface.unzip();
+
+        // Start populating the bind_groups
+        let mut universal_bind_groups = vec![];
+        let mut bind_group_layouts = vec![];
+
+        // Load the letter texture into the gpu
+        let letter_texture = texture::GpuTexture::from_rgbatexture( &init_content.letter_texture, &device, &queue, "letter_texture", false, true, texture::SamplerConfig::default() );
+        let letter_normal_texture = create_normal_texture(&device, &queue, init_content.letter_normal_texture.as_ref());
+
+        // Create the bind group
+
+        let texture_bind_group_layout = create_texture_bind_group_layout(&device);
+        let texture_bind_group = create_texture_bind_group(&device, &texture_bind_group_layout, &letter_texture, &letter_normal_texture);
+        bind_group_layouts.push(&texture_bind_group_layout);
+        universal_bind_groups.push(texture_bind_group);
+
+        // Camera initialization
+        let camera = Camera::new_default(size.width as f32 / size.height as f32);
+        let (camera_uniform, inverse_camera_mat) = camera.create_matrices();
+
+        let camera_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("camera_buffer"),
+                contents: bytemuck::cast_slice(&[camera_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let camera_bind_group_layout = create_camera_bind_group_layout(&device);
+        let camera_bind_group = create_camera_bind_group(&device, &camera_bind_group_layout, &camera_buffer);
+        bind_group_layouts.push(&camera_bind_group_layout);
+        universal_bind_groups.push(camera_bind_group);
+
+        // Initialize the models
+        let glyph_map = GlyphMap::default();
+        let models = create_models(&device, &init_content.text, &init_content.layout, &glyph_map, &init_content.alphabet_models);
+        let glyph_bounds = std::array::from_fn(|i| init_content.alphabet_models[i].bounds());
+
+        // Displacement buffer handling
+        let initial = init_content.initial_displacement;
+        let initial_displacement = DisplacementUniform::initial(initial, init_content.layout.z_plane, inverse_camera_mat);
+        let displacement_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("displacement_buffer"),
+                contents: bytemuck::cast_slice(&[initial_displacement]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let time_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("time_buffer"),
+                contents: bytemuck::cast_slice(&[0.0f32, 0.0, 0.0, 0.0]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let size_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("size_buffer"),
+                contents: bytemuck::cast_slice(&[size.width as f32, size.height as f32, 0.0, 0.0]), // The last 2 0's are to pad up to 16 bytes
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let mut light_uniform = LightUniform::new([2.0, 0.5, 1000.0], [1.0, 1.0, 1.0]);
+        light_uniform.normal_map = init_content.letter_normal_texture.is_some() as u32;
+        let light_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("light_buffer"),
+                contents: bytemuck::cast_slice(&[light_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let gradient_uniform = GradientUniform::new([1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 1.0], false);
+        let gradient_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("gradient_buffer"),
+                contents: bytemuck::cast_slice(&[gradient_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let outline_uniform = OutlineUniform::new([0.0, 0.0, 0.0, 1.0], 0.05);
+        let outline_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("outline_buffer"),
+                contents: bytemuck::cast_slice(&[outline_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let scroll_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("scroll_buffer"),
+                contents: bytemuck::cast_slice(&[0.0f32, 0.0, 0.0, 0.0]), // A width of 0.0 disables the wrapping
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let displacement_mode_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("displacement_mode_buffer"),
+                contents: bytemuck::cast_slice(&[DisplacementMode::default().uniform(false)]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let misc_bind_group_layout = create_misc_bind_group_layout(&device);
+        let misc_bind_group = create_misc_bind_group(
+            &device,
+            &misc_bind_group_layout,
+            [&displacement_buffer, &time_buffer, &size_buffer, &light_buffer, &gradient_buffer, &scroll_buffer, &displacement_mode_buffer, &outline_buffer],
+        );
+        bind_group_layouts.push(&misc_bind_group_layout);
+        universal_bind_groups.push(misc_bind_group);
+
+
+        //Create the Render Pipeline
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("render_pipeline_layout"),
+            bind_group_layouts: bind_group_layouts.as_slice(),
+            push_constant_ranges: &[],
+        });
+
+        let pipeline_config = PipelineConfig::default();
+        let render_pipeline = create_render_pipeline(&device, &render_pipeline_layout, &shader, surface_format, &pipeline_config, false);
+        let depth_target = create_depth_target(&device, &pipeline_config, size);
+        let timer = gpu_timer::supported(&device).then(|| gpu_timer::GpuTimer::new(&device, &queue));
+
+        let mut state = State {
+            start_time: web_time::Instant::now(),
+            paused: false,
+            paused_at: web_time::Instant::now(),
+            paused_duration: web_time::Duration::ZERO,
+            time_buffer,
+            cursor_clicked: false,
+            cursor_pos: initial.focus,
+            cursor_on_window: false,
+            touch_id: 0,
+            modifiers: winit::keyboard::ModifiersState::empty(),
+            size_buffer,
+            camera,
+            camera_uniform,
+            inverse_camera_mat,
+            camera_buffer,
+            light_uniform,
+            light_buffer,
+            focus_points: vec![FocusPoint { position: initial.focus, strength: initial.strength }],
+            focus_half_life: DEFAULT_FOCUS_HALF_LIFE,
+            displacement_buffer,
+            gradient_uniform,
+            gradient_buffer,
+            outline_uniform,
+            outline_buffer,
+            marquee_speed: 0.0,
+            scroll_offset: 0.0,
+            scroll_buffer,
+            displacement_mode: DisplacementMode::default(),
+            displacement_mode_buffer,
+            last_frame_time: web_time::Instant::now(),
+            average_frame_time: 0.0,
+            frame_rate_shown_at: web_time::Instant::now(),
+            saved_settings: Settings::default(),
+            settings_checked_at: web_time::Instant::now(),
+            window,
+            size,
+            scale_factor: 1.0,
+            decoration: TextDecoration::default(),
+            scissor: None,
+            render_scale: 1.0,
+            anti_aliasing: AntiAliasing::None,
+            shadow_blur: 4.0,
+            clear_color: wgpu::Color { r: 1.0, g: 1.0, b: 1.0, a: 0.0 },
+            layout: init_content.layout,
+            text: init_content.text.clone(),
+            alphabet_models: init_content.alphabet_models.clone(),
+            debug_colors: false,
+            show_bounds: false,
+            glyph_map,
+            sort_instances: false,
+            frustum_culling: true,
+            render_backend: RenderBackend::Instanced,
+            render_stats: RenderStats::default(),
+            glyph_bounds,
+            gpu: Gpu {
+                instance,
+                device,
+                queue,
+                surface,
+                surface_configured: false,
+                surface_suboptimal: false,
+                surface_errors: 0,
+                surface_format,
+                render_pipeline,
+                outline_pipeline: None,
+                render_pipeline_layout,
+                shader,
+                pipeline_config,
+                taa: None,
+                supersample: None,
+                msaa_target: None,
+                depth_target,
+                shadow: None,
+                compute_displacement: None,
+                timer,
+                format_features: adapter.get_texture_format_features(surface_format.add_srgb_suffix()).flags,
+                adapter_features: adapter.features(),
+                adapter_info,
+                adapter,
+                models,
+                merged: None,
+                decoration_model: None,
+                bounds_model: None,
+                universal_bind_groups,
+                texture_bind_group_layout,
+                camera_bind_group_layout,
+                misc_bind_group_layout,
+                letter_texture,
+                letter_normal_texture,
+            },
+        };
+
+        //Configure surface for the first time
+        state.configure_surface();
+        // Creates the multisampled target for MSAA, falling back to no anti-aliasing if the adapter can't
+        state.set_anti_aliasing(init_content.anti_aliasing);
+        Ok(state)
+    }
+
+    fn request_redraw(&self) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    // Creates the state for another window drawing the same text. Fails when the window's surface can't be
+    // drawn to with the shared pipeline
+    pub fn share_with_window(&self, window: Arc<Window>) -> Result<State, GpuError> {
+        let surface = self.gpu.instance.create_surface(window.clone()).map_err(GpuError::Surface)?;
+        if !surface.get_capabilities(&self.gpu.adapter).formats.contains(&self.gpu.surface_format) {
+            return Err(GpuError::UnsupportedFormat(self.gpu.surface_format));
+        }
+        let size = window.inner_size();
+        let mut state = self.share(Some((window.clone(), surface)), size);
+        state.scale_factor = window.scale_factor();
+        Ok(state)
+    }
+
+    // The device, pipeline, glyph geometry and textures are shared. The surface, the offscreen targets, the camera
+    // and size uniforms and the instance buffers are new, so each window can lay out and show its own text
+    fn share(&self, window_surface: Option<(Arc<Window>, wgpu::Surface<'static>)>, size: winit::dpi::PhysicalSize<u32>) -> State {
+        let device = &self.gpu.device;
+        let (window, surface) = window_surface.unzip();
+
+        let mut camera = self.camera.clone();
+        camera.set_aspect(size.width as f32 / size.height.max(1) as f32);
+        let (camera_uniform, inverse_camera_mat) = camera.create_matrices();
+        let camera_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("camera_buffer"),
+                contents: bytemuck::cast_slice(&[camera_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let size_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("size_buffer"),
+                contents: bytemuck::cast_slice(&[size.width as f32, size.height as f32, 0.0, 0.0]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let mut models = self.gpu.models.clone();
+        for (i, model) in models.iter_mut().enumerate() {
+            model.instance_buffer = create_instance_buffer(device, &model.instances, i);
+        }
+        // The texture bind group is shared, the camera and misc groups point at this window's buffers
+        let mut universal_bind_groups = self.gpu.universal_bind_groups.clone();
+        universal_bind_groups[1] = create_camera_bind_group(device, &self.gpu.camera_bind_group_layout, &camera_buffer);
+        universal_bind_groups[2] = create_misc_bind_group(
+            device,
+            &self.gpu.misc_bind_group_layout,
+            [
+                &self.displacement_buffer,
+                &self.time_buffer,
+                &size_buffer,
+                &self.light_buffer,
+                &self.gradient_buffer,
+                &self.scroll_buffer,
+                &self.displacement_mode_buffer,
+                &self.outline_buffer,
+            ],
+        );
+
+        let mut state = State {
+            window,
+            size,
+            scale_factor: 1.0,
+            gpu: Gpu {
+                instance: self.gpu.instance.clone(),
+                adapter: self.gpu.adapter.clone(),
+                device: device.clone(),
+                queue: self.gpu.queue.clone(),
+                surface,
+                surface_configured: false,
+                surface_suboptimal: false,
+                surface_errors: 0,
+                surface_format: self.gpu.surface_format,
+                render_pipeline: self.gpu.render_pipeline.clone(),
+                outline_pipeline: self.gpu.outline_pipeline.clone(),
+                render_pipeline_layout: self.gpu.render_pipeline_layout.clone(),
+                shader: self.gpu.shader.clone(),
+                pipeline_config: self.gpu.pipeline_config,
+                taa: None,
+                supersample: None,
+                msaa_target: None,
+                depth_target: create_depth_target(device, &self.gpu.pipeline_config, size),
+                shadow: None,
+                compute_displacement: None,
+                // Each window times its own passes
+                timer: gpu_timer::supported(device).then(|| gpu_timer::GpuTimer::new(device, &self.gpu.queue)),
+                format_features: self.gpu.format_features,
+                adapter_features: self.gpu.adapter_features,
+                adapter_info: self.gpu.adapter_info.clone(),
+                models,
+                merged: None,
+                decoration_model: self.gpu.decoration_model.clone(),
+                bounds_model: self.gpu.bounds_model.clone(),
+                universal_bind_groups,
+                texture_bind_group_layout: self.gpu.texture_bind_group_layout.clone(),
+                camera_bind_group_layout: self.gpu.camera_bind_group_layout.clone(),
+                misc_bind_group_layout: self.gpu.misc_bind_group_layout.clone(),
+                letter_texture: self.gpu.letter_texture.clone(),
+                letter_normal_texture: self.gpu.letter_normal_texture.clone(),
+            },
+            decoration: self.decoration,
+            scissor: None,
+            render_scale: 1.0,
+            anti_aliasing: self.anti_aliasing,
+            shadow_blur: self.shadow_blur,
+            clear_color: self.clear_color,
+            layout: self.layout,
+            text: self.text.clone(),
+            alphabet_models: self.alphabet_models.clone(),
+            debug_colors: self.debug_colors,
+            show_bounds: self.show_bounds,
+            glyph_map: self.glyph_map.clone(),
+            sort_instances: self.sort_instances,
+            frustum_culling: self.frustum_culling,
+            render_backend: self.render_backend,
+            render_stats: RenderStats::default(),
+            glyph_bounds: self.glyph_bounds,
+            start_time: self.start_time,
+            paused: self.paused,
+            paused_at: self.paused_at,
+            paused_duration: self.paused_duration,
+            time_buffer: self.time_buffer.clone(),
+            size_buffer,
+            cursor_clicked: false,
+            cursor_pos: self.cursor_pos,
+            cursor_on_window: false,
+            touch_id: 0,
+            modifiers: winit::keyboard::ModifiersState::empty(),
+            camera,
+            camera_uniform,
+            inverse_camera_mat,
+            camera_buffer,
+            light_uniform: self.light_uniform,
+            light_buffer: self.light_buffer.clone(),
+            focus_points: self.focus_points.clone(),
+            focus_half_life: self.focus_half_life,
+            displacement_buffer: self.displacement_buffer.clone(),
+            gradient_uniform: self.gradient_uniform,
+            gradient_buffer: self.gradient_buffer.clone(),
+            outline_uniform: self.outline_uniform,
+            outline_buffer: self.outline_buffer.clone(),
+            marquee_speed: self.marquee_speed,
+            scroll_offset: self.scroll_offset,
+            scroll_buffer: self.scroll_buffer.clone(),
+            displacement_mode: self.displacement_mode,
+            displacement_mode_buffer: self.displacement_mode_buffer.clone(),
+            last_frame_time: web_time::Instant::now(),
+            average_frame_time: 0.0,
+            frame_rate_shown_at: web_time::Instant::now(),
+            saved_settings: self.saved_settings.clone(),
+            settings_checked_at: web_time::Instant::now(),
+        };
+        // The offscreen targets depend on the window's size, the merged buffers hold this window's instances
+        state.set_render_backend(self.render_backend);
+        state.set_render_scale(self.render_scale);
+        if self.gpu.taa.is_some() {
+            state.set_taa(true);
+        }
+        state.set_drop_shadow(self.gpu.shadow.is_some());
+        if self.gpu.compute_displacement.is_some() {
+            state.set_compute_displacement(true);
+        }
+        state.configure_surface();
+        state
+    }
+
+    // Everything the adapter supports, check before requesting an optional feature
+    pub fn supported_features(&self) -> wgpu::Features {
+        self.gpu.adapter_features
+    }
+
+    pub fn backend(&self) -> wgpu::Backend {
+        self.gpu.adapter_info.backend
+    }
+
+    // Measures the world-space size text will take up once rendered, without creating any gpu buffers
+    pub fn measure_text(&self, text: &str) -> TextMetrics {
+        measure_text(text, &self.layout, &self.glyph_map, &self.glyph_bounds)
+    }
+
+    // Fills the letters with a vertical gradient instead of the letter texture
+    pub fn set_gradient(&mut self, top: [f32; 4], bottom: [f32; 4]) {
+        self.gradient_uniform = GradientUniform::new(top, bottom, true);
+        self.gpu.queue.write_buffer(&self.gradient_buffer, 0, bytemuck::cast_slice(&[self.gradient_uniform]));
+    }
+
+    pub fn clear_gradient(&mut self) {
+        self.gradient_uniform.enabled = 0;
+        self.gpu.queue.write_buffer(&self.gradient_buffer, 0, bytemuck::cast_slice(&[self.gradient_uniform]));
+    }
+
+    // Gives a single letter its own texture, or returns it to the shared letter texture with None
+    pub fn set_letter_texture(&mut self, letter_index: usize, texture: Option<&texture::RgbaTexture<[u8; 4]>>) {
+        self.gpu.models[letter_index].texture = texture.map(|rgba| create_model_texture(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &self.gpu.texture_bind_group_layout,
+            rgba,
+            &self.gpu.letter_normal_texture,
+            letter_index,
+        ));
+    }
+
+    // Perturbs the lighting normals with a normal map sampled at the letters' texture coordinates,
+    // or turns that off and binds a flat 1x1 normal texture with None
+    pub fn set_normal_map(&mut self, normal_map: Option<&texture::RgbaTexture<[u8; 4]>>) {
+        self.gpu.letter_normal_texture = create_normal_texture(&self.gpu.device, &self.gpu.queue, normal_map);
+        // Every texture bind group samples the normal texture
+        self.gpu.universal_bind_groups[0] = create_texture_bind_group(
+            &self.gpu.device,
+            &self.gpu.texture_bind_group_layout,
+            &self.gpu.letter_texture,
+            &self.gpu.letter_normal_texture,
+        );
+        for model in &mut self.gpu.models {
+            if let Some(model_texture) = &mut model.texture {
+                model_texture.bind_group = create_texture_bind_group(
+                    &self.gpu.device,
+                    &self.gpu.texture_bind_group_layout,
+                    &model_texture.texture,
+                    &self.gpu.letter_normal_texture,
+                );
+            }
+        }
+        self.light_uniform.normal_map = normal_map.is_some() as u32;
+        self.gpu.queue.write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&[self.light_uniform]));
+    }
+
+    // Clips drawing to a rectangle of the surface, given in physical pixels. The part past the surface's edges is
+    // cut off, and the rectangle is kept within the surface as it's resized. A rectangle that doesn't overlap the
+    // surface would clip everything, so it's rejected and the previous scissor is kept
+    pub fn set_scissor(&mut self, rect: Option<(u32, u32, u32, u32)>) -> Result<(), ScissorOutsideSurface> {
+        let clamped = rect.map(|rect| scissor_in(rect, self.size, self.size));
+        if let (Some(rect), Some((_, _, 0, _) | (_, _, _, 0))) = (rect, clamped) {
+            return Err(ScissorOutsideSurface { rect, surface: self.size });
+        }
+        self.scissor = clamped;
+        Ok(())
+    }
+
+    // The size of the texture the scene is rendered into
+    fn scene_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        match &self.gpu.supersample {
+            Some(supersample) => {
+                let size = supersample.target_size();
+                winit::dpi::PhysicalSize::new(size.width, size.height)
+            }
+            None => self.size,
+        }
+    }
+
+    // The scissor rectangle in the pixels of the scene's render target, clamped to fit inside it
+    fn scissor_in_target(&self) -> Option<(u32, u32, u32, u32)> {
+        Some(scissor_in(self.scissor?, self.size, self.scene_size()))
+    }
+
+    // Changes which glyph each char is drawn with and lays the text out again
+    pub fn set_glyph_map(&mut self, glyph_map: GlyphMap) {
+        self.glyph_map = glyph_map;
+        self.rebuild_text();
+    }
+
+    // Replaces the displayed text. Each glyph's instance buffer is rewritten in place when the new
+    // instances fit and only reallocated when it has to grow
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.rebuild_text();
+    }
+
+    // Edits the text with a key press: typed chars are appended, backspace removes the last char and
+    // enter starts a new line. Chars without a glyph are dropped, and the text is only laid out again
+    // when the key changed it
+    fn edit_text(&mut self, key: &winit::keyboard::Key, typed: Option<&str>) {
+        use winit::keyboard::{Key, NamedKey};
+        let mut text = self.text.clone();
+        match key {
+            Key::Named(NamedKey::Backspace) => {
+                text.pop();
+            }
+            Key::Named(NamedKey::Enter) => text.push('\n'),
+            _ => text.extend(typed.unwrap_or_default().chars().filter(|&c| c == ' ' || self.glyph_map.glyph_index(c).is_some())),
+        }
+        if text != self.text {
+            self.set_text(&text);
+        }
+    }
+
+    // Shows text as a block centered on a point in the world, e.g. to label something in the scene
+    pub fn place_text_at(&mut self, world_pos: cgmath::Point3<f32>, text: &str) {
+        self.text = text.to_string();
+        self.layout.z_plane = world_pos.z;
+        self.layout.center = Some([world_pos.x, world_pos.y]);
+        self.rebuild_text();
+    }
+
+    // Numbers each line in a gutter to the left of the text, see LayoutConfig::line_numbers
+    pub fn set_line_numbers(&mut self, enabled: bool) {
+        self.layout.line_numbers = enabled;
+        self.rebuild_text();
+    }
+
+    // Tints the letters across the whole text, see LayoutConfig::color_gradient
+    pub fn set_color_gradient(&mut self, gradient: Option<TextGradient>) {
+        self.layout.color_gradient = gradient;
+        self.rebuild_text();
+    }
+
+    pub fn set_sort_instances(&mut self, enabled: bool) {
+        self.sort_instances = enabled;
+    }
+
+    // Tints every instance of a letter with that letter's debug_color, or back to the layout's color_gradient
+    pub fn set_debug_colors(&mut self, enabled: bool) {
+        self.debug_colors = enabled;
+        if enabled {
+            for (i, model) in self.gpu.models.iter_mut().enumerate() {
+                for instance in &mut model.instances {
+                    instance.color = debug_color(i);
+                }
+            }
+        } else {
+            color_instances(self.gpu.models.iter_mut().flat_map(|m| &mut m.instances).collect(), self.layout.color_gradient);
+        }
+        for model in &self.gpu.models {
+            let instance_data: Vec<InstanceRaw> = model.instances.iter().map(Instance::to_raw).collect();
+            self.gpu.queue.write_buffer(&model.instance_buffer, 0, bytemuck::cast_slice(&instance_data));
+        }
+        self.set_render_backend(self.render_backend);
+    }
+
+    // Switches which buffers the letters are drawn from, see render_stats to compare them.
+    // Also repacks the merged buffers after the models change
+    pub fn set_render_backend(&mut self, backend: RenderBackend) {
+        self.render_backend = backend;
+        // Dropping the old merged buffers frees them
+        self.gpu.merged = (backend == RenderBackend::Merged)
+            .then(|| create_merged_models(&self.gpu.device, &self.gpu.models, &self.alphabet_models));
+        self.write_displacement_mode();
+    }
+
+    // What was drawn in the last frame
+    pub fn render_stats(&self) -> RenderStats {
+        self.render_stats
+    }
+
+    // Milliseconds the gpu spent on a recent frame's main pass. None without timestamp queries (e.g. WebGL2)
+    // or until the first measurement is read back
+    pub fn gpu_time(&self) -> Option<f32> {
+        self.gpu.timer.as_ref().and_then(|timer| timer.last_time())
+    }
+
+    // Picks the function the vertex shader displaces the letters around the focus with
+    pub fn set_displacement_mode(&mut self, mode: DisplacementMode) {
+        self.displacement_mode = mode;
+        self.write_displacement_mode();
+    }
+
+    // Moves the letters in a compute pass instead of the vertex shader, see displace.rs. The compute pass only
+    // applies to RenderBackend::Instanced. Returns false, leaving it off, when the device can't run compute shaders
+    pub fn set_compute_displacement(&mut self, enabled: bool) -> bool {
+        if enabled && !displace::supported(&self.gpu.device) {
+            platform_specific::print("Compute shaders aren't supported here, the vertex shader keeps displacing the letters");
+            return false;
+        }
+        self.gpu.compute_displacement = enabled.then(|| displace::ComputeDisplacement::new(&self.gpu.device, uniform_size::<DisplacementUniform>()));
+        self.write_displacement_mode();
+        true
+    }
+
+    // Skips drawing the letter instances outside the camera's view. The marquee turns culling off while it scrolls
+    pub fn set_frustum_culling(&mut self, enabled: bool) {
+        self.frustum_culling = enabled;
+    }
+
+    // How far the shaders can move a letter from where its instance places it: the idle wave, plus every
+    // focus point's displacement
+    fn cull_margin(&self) -> f32 {
+        CULL_WAVE_MARGIN + CULL_FOCUS_MARGIN * self.focus_points.iter().map(|p| p.strength.abs()).sum::<f32>()
+    }
+
+    // Draws the edges of the letters' tris instead of filling them, to see how the helpers in letters.rs
+    // triangulate. Returns false, leaving the letters filled, when the device can't draw lines (like WebGL2)
+    pub fn set_wireframe(&mut self, enabled: bool) -> bool {
+        if enabled && !self.gpu.device.features().contains(wgpu::Features::POLYGON_MODE_LINE) {
+            platform_specific::print("Wireframes aren't supported here, the letters stay filled");
+            return false;
+        }
+        self.gpu.pipeline_config.wireframe = enabled;
+        self.rebuild_pipeline();
+        true
+    }
+
+    // Whether the compute pass displaces the letters this frame, in which case the vertex shader doesn't
+    fn compute_displacement_active(&self) -> bool {
+        self.gpu.compute_displacement.is_some() && self.render_backend == RenderBackend::Instanced
+    }
+
+    fn write_displacement_mode(&self) {
+        let uniform = self.displacement_mode.uniform(self.compute_displacement_active());
+        self.gpu.queue.write_buffer(&self.displacement_mode_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // How long the displacement focus takes to close half the distance to the cursor, in seconds.
+    // 0.0 snaps it to the cursor
+    pub fn set_focus_half_life(&mut self, seconds: f32) {
+        self.focus_half_life = seconds.max(0.0);
+    }
+
+    // The displacement's current (focus, strength), as last stepped by render. The focus is in normalized
+    // device coordinates like cursor_pos
+    pub fn displacement_state(&self) -> ([f32; 2], f32) {
+        (self.focus_points[0].position, self.focus_points[0].strength)
+    }
+
+    // Displaces the letters around these points as well as the one following the cursor, e.g. for scripted
+    // effects. Only the first MAX_FOCUS_POINTS - 1 are kept
+    pub fn set_extra_focus_points(&mut self, points: &[FocusPoint]) {
+        self.focus_points.truncate(1);
+        self.focus_points.extend(points.iter().take(MAX_FOCUS_POINTS - 1));
+        self.write_focus_points();
+    }
+
+    // Uploads the focus points, moved onto the plane the text is laid out on
+    fn write_focus_points(&self) {
+        let points: Vec<[f32; 4]> = self.focus_points.iter().map(|point| {
+            let position = Camera::find_3d_mouse_pos(point.position, self.layout.z_plane, self.inverse_camera_mat);
+            [position.x, position.y, position.z, point.strength]
+        }).collect();
+        self.gpu.queue.write_buffer(&self.displacement_buffer, 0, bytemuck::cast_slice(&[DisplacementUniform::new(&points)]));
+    }
+
+    // Scrolls the text to the left at speed world units per second, letters leaving the left edge
+    // come back in from the right. A speed of 0.0 stops the marquee and puts the text back in place
+    pub fn set_marquee(&mut self, speed: f32) {
+        self.marquee_speed = speed;
+        if speed == 0.0 {
+            self.scroll_offset = 0.0;
+        }
+        self.write_scroll();
+    }
+
+    fn advance_scroll(&mut self, dt: f32) {
+        let wrap_width = RIGHT_BOUND - LEFT_BOUND + 2.0 * MARQUEE_MARGIN;
+        self.scroll_offset = (self.scroll_offset + self.marquee_speed * dt).rem_euclid(wrap_width);
+    }
+
+    fn write_scroll(&self) {
+        // The shader wraps x into [left, left + width), a width of 0.0 turns the wrapping off
+        let (left, width) = if self.marquee_speed != 0.0 {
+            (LEFT_BOUND - MARQUEE_MARGIN, RIGHT_BOUND - LEFT_BOUND + 2.0 * MARQUEE_MARGIN)
+        } else {
+            (0.0, 0.0)
+        };
+        self.gpu.queue.write_buffer(&self.scroll_buffer, 0, bytemuck::cast_slice(&[self.scroll_offset, left, width, 0.0]));
+    }
+
+    // Lays the text out again with the current layout, keeping each letter's texture
+    fn rebuild_text(&mut self) {
+        let instances_list = layout_instances(&self.text, &self.layout, &self.glyph_map);
+        for (i, (model, instances)) in self.gpu.models.iter_mut().zip(instances_list).enumerate() {
+            upload_instances(&self.gpu.device, &self.gpu.queue, model, instances, i);
+        }
+        self.gpu.decoration_model = create_decoration_model(&self.gpu.device, &self.gpu.models, &self.layout, &self.decoration);
+        if self.debug_colors {
+            self.set_debug_colors(true);
+        } else {
+            self.set_render_backend(self.render_backend);
+        }
+        self.set_show_bounds(self.show_bounds);
+    }
+
+    // Draws an outline around the bounds of every letter over the text
+    pub fn set_show_bounds(&mut self, enabled: bool) {
+        self.show_bounds = enabled;
+        self.gpu.bounds_model = if enabled {
+            create_bounds_model(&self.gpu.device, &self.gpu.models, &self.glyph_bounds, self.layout.z_plane + OVERLAY_Z_OFFSET)
+        } else {
+            None
+        };
+    }
+
+    pub fn apply_scene(&mut self, scene: &Scene) {
+        platform_specific::print(&format!("Switching to the {} scene", scene.name));
+        self.apply_config(&scene.config);
+    }
+
+    pub fn config_snapshot(&self) -> StateConfig {
+        StateConfig {
+            text: self.text.clone(),
+            layout: self.layout,
+            camera_eye: self.camera.eye.into(),
+            camera_target: self.camera.target.into(),
+            projection: self.camera.projection,
+            displacement_focus: self.focus_points[0].position,
+            displacement_strength: self.focus_points[0].strength,
+            gradient: (self.gradient_uniform.enabled != 0).then_some((self.gradient_uniform.top, self.gradient_uniform.bottom)),
+            decoration: self.decoration,
+            marquee_speed: self.marquee_speed,
+            debug_colors: self.debug_colors,
+            show_bounds: self.show_bounds,
+            anti_aliasing: self.anti_aliasing,
+            clear_color: [self.clear_color.r, self.clear_color.g, self.clear_color.b, self.clear_color.a],
+            drop_shadow: self.gpu.shadow.is_some().then_some(self.shadow_blur),
+            outline: self.gpu.outline_pipeline.is_some().then_some((self.outline_uniform.color, self.outline_uniform.width)),
+        }
+    }
+
+    // Reconfigures everything in config, rebuilding the text and pipeline
+    pub fn apply_config(&mut self, config: &StateConfig) {
+        self.text = config.text.clone();
+        self.layout = config.layout;
+        self.decoration = config.decoration;
+        self.debug_colors = config.debug_colors;
+        self.show_bounds = config.show_bounds;
+        self.rebuild_text();
+        if !config.debug_colors {
+            self.set_debug_colors(false);
+        }
+
+        self.camera.eye = config.camera_eye.into();
+        self.camera.target = config.camera_target.into();
+        self.camera.update_view();
+        self.camera.set_projection(config.projection);
+
+        self.focus_points[0] = FocusPoint { position: config.displacement_focus, strength: config.displacement_strength };
+
+        match config.gradient {
+            Some((top, bottom)) => self.set_gradient(top, bottom),
+            None => self.clear_gradient(),
+        }
+        self.set_marquee(config.marquee_speed);
+        self.set_anti_aliasing(config.anti_aliasing);
+
+        let [r, g, b, a] = config.clear_color;
+        self.set_clear_color(wgpu::Color { r, g, b, a });
+        if let Some(radius) = config.drop_shadow {
+            self.set_shadow_blur(radius);
+        }
+        self.set_drop_shadow(config.drop_shadow.is_some());
+        if let Some((color, width)) = config.outline {
+            self.set_outline_style(color, width);
+        }
+        self.set_outline(config.outline.is_some());
+    }
+
+    pub fn set_decoration(&mut self, decoration: TextDecoration) {
+        self.decoration = decoration;
+        self.gpu.decoration_model = create_decoration_model(&self.gpu.device, &self.gpu.models, &self.layout, &decoration);
+    }
+
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.gpu.pipeline_config.blend_mode = mode;
+        self.rebuild_pipeline();
+    }
+
+    pub fn set_alpha_to_coverage(&mut self, enabled: bool) {
+        self.gpu.pipeline_config.alpha_to_coverage = enabled;
+        self.rebuild_pipeline();
+    }
+
+    // Negative constants move the text toward the camera. See PipelineConfig::depth_bias
+    pub fn set_depth_bias(&mut self, bias: wgpu::DepthBiasState) {
+        self.gpu.pipeline_config.depth_bias = bias;
+        self.rebuild_pipeline();
+    }
+
+    // See PipelineConfig::sample_mask, !0 writes every sample
+    pub fn set_sample_mask(&mut self, mask: u64) {
+        self.gpu.pipeline_config.sample_mask = mask;
+        self.rebuild_pipeline();
+    }
+
+    // Kept private so anti_aliasing always says which technique is on, see set_anti_aliasing
+    fn set_taa(&mut self, enabled: bool) {
+        self.gpu.taa = if enabled {
+            Some(taa::Taa::new(&self.gpu.device, self.gpu.surface_format.add_srgb_suffix(), self.size))
+        } else {
+            // Restore the unjittered camera
+            self.gpu.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
+            None
+        };
+    }
+
+    // A scale of 1.0 renders directly into the surface, anything else renders offscreen and downsamples.
+    // Kept private like set_taa, set_anti_aliasing checks the scale is positive and finite
+    fn set_render_scale(&mut self, scale: f32) {
+        self.render_scale = scale;
+        self.gpu.supersample = (scale != 1.0).then(|| {
+            supersample::Supersample::new(&self.gpu.device, self.gpu.surface_format.add_srgb_suffix(), self.size, scale)
+        });
+        self.recreate_scene_targets();
+    }
+
+    // Picks the anti-aliasing technique, turning off the others. Modes the adapter can't do fall back to None
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) {
+        let anti_aliasing = match anti_aliasing {
+            AntiAliasing::Msaa(count) if !self.gpu.format_features.sample_count_supported(count) => {
+                platform_specific::print(&format!("{count}x MSAA isn't supported by the adapter, anti-aliasing is off"));
+                AntiAliasing::None
+            }
+            AntiAliasing::Sdf => {
+                platform_specific::print("SDF glyphs aren't supported yet, anti-aliasing is off");
+                AntiAliasing::None
+            }
+            // Scales that aren't positive and finite would leave nothing to render into
+            AntiAliasing::Supersample(scale) if !(scale.is_finite() && scale > 0.0) => {
+                platform_specific::print(&format!("Can't supersample at a scale of {scale}, anti-aliasing is off"));
+                AntiAliasing::None
+            }
+            other => other,
+        };
+        self.anti_aliasing = anti_aliasing;
+
+        if (anti_aliasing == AntiAliasing::Taa) != self.gpu.taa.is_some() {
+            self.set_taa(anti_aliasing == AntiAliasing::Taa);
+        }
+        self.gpu.pipeline_config.sample_count = match anti_aliasing {
+            AntiAliasing::Msaa(count) => count,
+            _ => 1,
+        };
+        self.rebuild_pipeline();
+        // Recreates the scene targets for the new sample count and scale
+        self.set_render_scale(match anti_aliasing {
+            AntiAliasing::Supersample(scale) => scale,
+            _ => 1.0,
+        });
+    }
+
+    // Keeps the multisampled target, the depth target and the shadow matching the sample count and the size of the scene target
+    fn recreate_scene_targets(&mut self) {
+        let sample_count = self.gpu.pipeline_config.sample_count;
+        self.gpu.depth_target = create_depth_target(&self.gpu.device, &self.gpu.pipeline_config, self.scene_size());
+        if self.gpu.shadow.is_some() {
+            self.gpu.shadow = Some(self.create_shadow());
+        }
+        self.gpu.msaa_target = (sample_count > 1).then(|| {
+            let size = self.scene_size();
+            texture::GpuTexture::multisampled_target(
+                &self.gpu.device,
+                self.gpu.surface_format.add_srgb_suffix(),
+                size.width,
+                size.height,
+                sample_count,
+                "msaa_target",
+            )
+        });
+    }
+
+    // Draws a soft shadow under the letters, blurred by shadow_blur
+    pub fn set_drop_shadow(&mut self, enabled: bool) {
+        self.gpu.shadow = enabled.then(|| self.create_shadow());
+    }
+
+    pub fn set_clear_color(&mut self, color: wgpu::Color) {
+        self.clear_color = color;
+    }
+
+    fn settings(&self) -> Settings {
+        let color = self.clear_color;
+        Settings {
+            camera_eye: self.camera.eye.into(),
+            camera_target: self.camera.target.into(),
+            projection: self.camera.projection,
+            clear_color: [color.r, color.g, color.b, color.a],
+            displacement_mode: self.displacement_mode,
+            focus_half_life: self.focus_half_life,
+        }
+    }
+
+    fn apply_settings(&mut self, settings: &Settings) {
+        self.camera.eye = settings.camera_eye.into();
+        self.camera.target = settings.camera_target.into();
+        self.camera.update_view();
+        self.camera.set_projection(settings.projection);
+        let [r, g, b, a] = settings.clear_color;
+        self.set_clear_color(wgpu::Color { r, g, b, a });
+        self.set_displacement_mode(settings.displacement_mode);
+        self.set_focus_half_life(settings.focus_half_life);
+    }
+
+    // Saves the settings if they changed since they were last saved
+    fn save_settings(&mut self) {
+        // Settings are saved for the windowed app, not for headless renders
+        if self.window.is_none() {
+            return;
+        }
+        let settings = self.settings();
+        if settings != self.saved_settings {
+            platform_specific::save_settings(&settings);
+            self.saved_settings = settings;
+        }
+    }
+
+    // Draws an outline behind the letters, styled by set_outline_style
+    pub fn set_outline(&mut self, enabled: bool) {
+        self.gpu.outline_pipeline = enabled.then(|| self.create_pipeline(true));
+    }
+
+    // width is how far the outline reaches past each side of a letter, in units of the letter's size.
+    // A translucent color gives a glow
+    pub fn set_outline_style(&mut self, color: [f32; 4], width: f32) {
+        self.outline_uniform = OutlineUniform::new(color, width.max(0.0));
+        self.gpu.queue.write_buffer(&self.outline_buffer, 0, bytemuck::cast_slice(&[self.outline_uniform]));
+    }
+
+    // The radius of the shadow's blur in pixels, 0.0 gives a hard shadow
+    pub fn set_shadow_blur(&mut self, radius: f32) {
+        self.shadow_blur = radius;
+        if let Some(shadow) = &mut self.gpu.shadow {
+            shadow.set_radius(&self.gpu.queue, radius);
+        }
+    }
+
+    fn create_shadow(&self) -> shadow::Shadow {
+        shadow::Shadow::new(
+            &self.gpu.device,
+            self.gpu.surface_format.add_srgb_suffix(),
+            self.scene_size(),
+            self.gpu.pipeline_config.sample_count,
+            self.gpu.pipeline_config.depth_format,
+            self.shadow_blur,
+        )
+    }
+
+    fn rebuild_pipeline(&mut self) {
+        self.gpu.render_pipeline = self.create_pipeline(false);
+        if self.gpu.outline_pipeline.is_some() {
+            self.gpu.outline_pipeline = Some(self.create_pipeline(true));
+        }
+    }
+
+    fn create_pipeline(&self, outline: bool) -> wgpu::RenderPipeline {
+        create_render_pipeline(
+            &self.gpu.device,
+            &self.gpu.render_pipeline_layout,
+            &self.gpu.shader,
+            self.gpu.surface_format,
+            &self.gpu.pipeline_config,
+            outline,
+        )
+    }
+
+    // Finds the letter instance under a cursor position (in normalized device coordinates).
+    // Returns the letter and the index of the instance within that letter's model
+    pub fn pick_letter(&self, cursor_pos: [f32; 2]) -> Option<(char, usize)> {
+        let hit = Camera::find_3d_mouse_pos(cursor_pos, self.layout.z_plane, self.inverse_camera_mat);
+        for (i, (model, bounds)) in self.gpu.models.iter().zip(&self.glyph_bounds).enumerate() {
+            let Some(bounds) = bounds else { continue };
+            for (j, instance) in model.instances.iter().enumerate() {
+                let inside = (0..2).all(|k| {
+                    hit[k] >= instance.position[k] + instance.scale * bounds.min[k]
+                        && hit[k] <= instance.position[k] + instance.scale * bounds.max[k]
+                });
+                if inside {
+                    return self.glyph_map.glyph_char(i).map(|c| (c, j));
+                }
+            }
+        }
+        None
+    }
+
+    fn configure_surface(&mut self) {
+        //If size is zero, do not reconfigure surface. Causes wgpu errors
+        if self.size.width == 0 || self.size.height == 0 { 
+            return;
+        }
+        let Some(surface) = &self.gpu.surface else { return };
+
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: self.gpu.surface_format,
+            //Request compatibility with the sRGB-format texture view we're going to create later
+            view_formats: vec![self.gpu.surface_format.add_srgb_suffix()],
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            width: self.size.width,
+            height: self.size.height,
+            desired_maximum_frame_latency: 2,
+            present_mode: wgpu::PresentMode::AutoVsync,
+        };
+        surface.configure(&self.gpu.device, &surface_config);
+        self.gpu.surface_configured = true;
+    }
+
+    // Switches between a perspective and an orthographic camera, keeping the text the same size on screen
+    pub fn toggle_projection(&mut self) {
+        let distance = (self.camera.eye - self.camera.target).magnitude();
+        self.camera.set_projection(self.camera.projection.toggled(distance));
+        self.reconfigure_camera();
+    }
+
+    // The camera can be moved freely, the uniform is rewritten on the next frame
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    // Rewrites the camera uniform from the camera's current state
+    fn reconfigure_camera(&mut self) {
+        (self.camera_uniform, self.inverse_camera_mat) = self.camera.create_matrices();
+        self.gpu.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
+        self.camera.dirty = false;
+    }
+
+    pub fn update_cursor(&mut self, position: winit::dpi::PhysicalPosition<f64>) {
+        let previous = self.cursor_pos;
+        self.cursor_pos = [
+            2.0 * (position.x as f32 / self.size.width as f32 - 0.5),
+            -2.0 * (position.y as f32 / self.size.height as f32 - 0.5),
+        ];
+        // Dragging orbits the camera, the scene turns the way the cursor moves
+        if self.cursor_clicked {
+            self.camera.orbit(
+                -ORBIT_SPEED * (self.cursor_pos[0] - previous[0]),
+                -ORBIT_SPEED * (self.cursor_pos[1] - previous[1]),
+            );
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        // A minimized window, or a canvas that isn't laid out yet, has no pixels. Keep the old targets until it does
+        if new_size.width == 0 || new_size.height == 0 {
+            return;
+        }
+        // On wasm this is the canvas's css size times the device pixel ratio, so the canvas renders at the
+        // display's full resolution. The canvas needs a css size of its own (see index.html), otherwise
+        // resizing its pixel buffer would also change its layout size and resize it again
+        self.size = new_size;
+        //Reconfigure the surface
+        self.configure_surface();
+        self.camera.set_aspect(self.size.width as f32 / self.size.height as f32);
+        if let Some(taa) = &mut self.gpu.taa {
+            taa.resize(&self.gpu.device, self.size);
+        }
+        if let Some(supersample) = &mut self.gpu.supersample {
+            supersample.resize(&self.gpu.device, self.size);
+        }
+        self.recreate_scene_targets();
+        // Update the size uniform
+        // The last 2 0's are to pad up to 16 bytes
+        self.gpu.queue.write_buffer(&self.size_buffer, 0, bytemuck::cast_slice(&[self.size.width as f32, self.size.height as f32, 0.0, 0.0]));
+    }
+
+    // Records the passes that draw the scene into scene_view: the shadow's passes when there's a shadow, then
+    // the letters and overlays. Returns what was drawn
+    fn encode_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        scene_view: &wgpu::TextureView,
+        camera: &Camera,
+        attachments: SceneAttachments,
+    ) -> RenderStats {
+        // Each letter, then the decorations and debug outlines on top.
+        // Sorted letters are drawn a run of instances at a time, from the back to the front
+        let mut letter_draws: Vec<(usize, std::ops::Range<u32>)> = if self.sort_instances {
+            back_to_front_draws(&self.gpu.models, camera.eye)
+        } else {
+            self.gpu.models.iter().enumerate().map(|(i, m)| (i, 0..m.instances.len() as u32)).collect()
+        };
+        // The marquee moves letters around in the vertex shader, away from where their instances are
+        if self.frustum_culling && self.marquee_speed == 0.0 {
+            let outline_scale = if self.gpu.outline_pipeline.is_some() { 1.0 + 2.0 * self.outline_uniform.width } else { 1.0 };
+            letter_draws = cull_draws(
+                letter_draws,
+                &self.gpu.models,
+                &self.glyph_bounds,
+                camera.build_view_projection_matrix(),
+                self.cull_margin(),
+                outline_scale,
+            );
+        }
+        let mut overlays: Vec<&Model> = self.gpu.decoration_model.iter().collect();
+        // The debug outlines don't cast a shadow
+        let shadow_overlay_count = overlays.len();
+        overlays.extend(&self.gpu.bounds_model);
+        let letter_buffers = match (&self.gpu.merged, &self.gpu.compute_displacement) {
+            (Some(merged), _) => LetterBuffers::Merged(merged),
+            (None, Some(displaced)) => LetterBuffers::Displaced(displaced),
+            (None, None) => LetterBuffers::Models,
+        };
+        if let LetterBuffers::Merged(merged) = letter_buffers {
+            merged.write_indirect_draws(&self.gpu.queue, &self.gpu.models, &letter_draws);
+        }
+        let mut stats = RenderStats::default();
+
+        // With MSAA the samples are drawn into the multisampled target and resolved into the view
+        let color_attachment = |view, clear_color| Some(wgpu::RenderPassColorAttachment {
+            view: attachments.msaa.unwrap_or(view),
+            resolve_target: attachments.msaa.map(|_| view),
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(clear_color),
+                store: wgpu::StoreOp::Store,
+            },
+        });
+        // Each pass clears the depth, nothing reads it afterwards
+        let depth_stencil_attachment = attachments.depth.map(|view| wgpu::RenderPassDepthStencilAttachment {
+            view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Discard,
+            }),
+            stencil_ops: None,
+        });
+
+        // The letters are drawn into the shadow's own target first, then blurred
+        if let Some(shadow) = attachments.shadow {
+            let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("shadow"),
+                // Only the alpha of the letters makes the shadow, so this is always cleared to transparent
+                color_attachments: &[color_attachment(shadow.target(), wgpu::Color::TRANSPARENT)],
+                depth_stencil_attachment: depth_stencil_attachment.clone(),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            renderpass.set_pipeline(&self.gpu.render_pipeline);
+            for (i, bind_group) in self.gpu.universal_bind_groups.iter().enumerate() {
+                renderpass.set_bind_group(i as u32, bind_group, &[]);
+            }
+            record_draws(
+                &mut renderpass,
+                &self.gpu.models,
+                &letter_draws,
+                letter_buffers,
+                &overlays[..shadow_overlay_count],
+                &self.gpu.universal_bind_groups[0],
+                &mut stats,
+            );
+            drop(renderpass);
+            shadow.blur(encoder);
+        }
+
+        //Create the render pass which will clear the screen
+        let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[color_attachment(scene_view, self.clear_color)],
+            depth_stencil_attachment,
+            timestamp_writes: attachments.timer.map(|timer| timer.timestamp_writes()),
+            occlusion_query_set: None,
+        });
+
+        // Draw commands
+        if let Some((x, y, width, height)) = attachments.scissor {
+            renderpass.set_scissor_rect(x, y, width, height);
+        }
+        if let Some(shadow) = attachments.shadow {
+            shadow.composite(&mut renderpass);
+        }
+        for (i, bind_group) in self.gpu.universal_bind_groups.iter().enumerate() {
+            renderpass.set_bind_group(i as u32, bind_group, &[]);
+        }
+        // The outline goes under every letter, the overlays aren't outlined
+        if let Some(outline_pipeline) = &self.gpu.outline_pipeline {
+            renderpass.set_pipeline(outline_pipeline);
+            record_draws(
+                &mut renderpass,
+                &self.gpu.models,
+                &letter_draws,
+                letter_buffers,
+                &[],
+                &self.gpu.universal_bind_groups[0],
+                &mut stats,
+            );
+        }
+        renderpass.set_pipeline(&self.gpu.render_pipeline);
+        record_draws(
+            &mut renderpass,
+            &self.gpu.models,
+            &letter_draws,
+            letter_buffers,
+            &overlays,
+            &self.gpu.universal_bind_groups[0],
+            &mut stats,
+        );
+        stats
+    }
+
+    // Renders the current frame into a width * height image instead of the window, e.g. for screenshots. Uses the
+    // same pipeline, models and scissor, but skips what has targets sized to the window: TAA, supersampling, and
+    // the drop shadow unless the image is the scene's size. Blocks until the gpu is done, which wasm can't do
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_to_image(&mut self, width: u32, height: u32) -> texture::RgbaTexture<[u8; 4]> {
+        let device = &self.gpu.device;
+        let format = self.gpu.surface_format.add_srgb_suffix();
+        let size = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("image_target"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&Default::default());
+        let sample_count = self.gpu.pipeline_config.sample_count;
+        let msaa = (sample_count > 1)
+            .then(|| texture::GpuTexture::multisampled_target(device, format, width, height, sample_count, "image_msaa_target"));
+        let depth = create_depth_target(device, &self.gpu.pipeline_config, winit::dpi::PhysicalSize::new(width, height));
+
+        // The camera is written for the image's aspect ratio, and rewritten for the window on the next frame
+        let mut camera = self.camera.clone();
+        camera.set_aspect(width as f32 / height as f32);
+        let (camera_uniform, _) = camera.create_matrices();
+        self.gpu.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[camera_uniform]));
+        self.camera.dirty = true;
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        let scissor = self.scissor.map(|rect| scissor_in(rect, self.size, winit::dpi::PhysicalSize::new(width, height)));
+        let shadow = self.gpu.shadow.as_ref().filter(|_| self.scene_size() == winit::dpi::PhysicalSize::new(width, height));
+        let attachments = SceneAttachments { msaa: msaa.as_ref(), depth: depth.as_ref(), shadow, scissor, timer: None };
+        self.encode_scene(&mut encoder, &view, &camera, attachments);
+
+        let bytes = texture::read_texture(device, &self.gpu.queue, encoder, &target);
+
+        let mut values: Vec<[u8; 4]> = bytemuck::cast_slice(&bytes).to_vec();
+        // Desktop surfaces are usually BGRA
+        if matches!(format, wgpu::TextureFormat::Bgra8UnormSrgb | wgpu::TextureFormat::Bgra8Unorm) {
+            for pixel in &mut values {
+                pixel.swap(0, 2);
+            }
+        }
+        texture::RgbaTexture {
+            values,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            height,
+            width,
+        }
+    }
+
+    // Steps the displacement toward the cursor and writes the time, displacement and scroll uniforms.
+    // Displacement lags behind the cursor position and grows as the cursor stays in one spot
+    fn advance_animation(&mut self, dt: f32) {
+        let seconds = self.animation_seconds();
+
+        // Exponential smoothing on the real frame time, so the lag feels the same at any frame rate
+        let cursor_focus = &mut self.focus_points[0];
+        let diff = [self.cursor_pos[0] - cursor_focus.position[0], self.cursor_pos[1] - cursor_focus.position[1]];
+        let t = focus_smoothing(dt, self.focus_half_life);
+        cursor_focus.position = [cursor_focus.position[0] + t * diff[0], cursor_focus.position[1] + t * diff[1]];
+
+        // The cap pulses slowly so the letters never settle completely
+        let max_strength = 0.4 + (0.06 * (f32::sin(seconds) + 1.0));
+        cursor_focus.strength = step_displacement_strength(cursor_focus.strength, dt, self.cursor_on_window, max_strength);
+
+        // Update uniforms
+        self.write_focus_points();
+        self.gpu.queue.write_buffer(&self.time_buffer, 0, bytemuck::cast_slice(&[seconds]));
+        if self.marquee_speed != 0.0 {
+            self.advance_scroll(dt);
+            self.write_scroll();
+        }
+    }
+
+    // Averages in the latest frame's time, and shows the frame rate about once every FRAME_RATE_INTERVAL
+    fn record_frame_time(&mut self, dt: f32) {
+        self.average_frame_time = if self.average_frame_time == 0.0 {
+            dt
+        } else {
+            self.average_frame_time + FRAME_TIME_SMOOTHING * (dt - self.average_frame_time)
+        };
+        if self.frame_rate_shown_at.elapsed().as_secs_f32() >= FRAME_RATE_INTERVAL {
+            self.frame_rate_shown_at = web_time::Instant::now();
+            platform_specific::show_frame_rate(self.average_frame_time, self.gpu_time());
+        }
+    }
+
+    // The average seconds between frames lately, 0.0 before the first frame
+    pub fn frame_time(&self) -> f32 {
+        self.average_frame_time
+    }
+
+    // Seconds since the state was created, not counting the time spent paused
+    pub fn animation_seconds(&self) -> f32 {
+        let now = if self.paused { self.paused_at } else { web_time::Instant::now() };
+        now.duration_since(self.start_time).saturating_sub(self.paused_duration).as_secs_f32()
+    }
+
+    // Freezes or resumes the animation. The time spent paused is skipped, so nothing jumps on resume
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused == self.paused {
+            return;
+        }
+        if paused {
+            self.paused_at = web_time::Instant::now();
+        } else {
+            self.paused_duration += self.paused_at.elapsed();
+        }
+        self.paused = paused;
+    }
+
+    // Recovers from a failed get_current_texture, the frame is skipped either way.
+    // See https://docs.rs/wgpu/24.0.1/wgpu/enum.SurfaceError.html
+    fn handle_surface_error(&mut self, error: wgpu::SurfaceError) {
+        if error == wgpu::SurfaceError::OutOfMemory {
+            panic!("Out of memory acquiring the next swapchain texture");
+        }
+        self.gpu.surface_errors += 1;
+        if self.gpu.surface_errors > MAX_SURFACE_ERRORS {
+            // Stop requesting redraws rather than failing every frame, resize configures the surface again
+            platform_specific::print(&format!("The surface failed {MAX_SURFACE_ERRORS} frames in a row ({error}), rendering stops until the window is resized"));
+            self.gpu.surface_configured = false;
+            self.gpu.surface_errors = 0;
+            return;
+        }
+        match error {
+            // e.g. after a resize, minimize or GPU reset. Reconfiguring gives the surface new textures
+            wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => self.configure_surface(),
+            _ => platform_specific::print(&format!("Skipping a frame, couldn't acquire the surface texture: {error}")),
+        }
+    }
+
+    pub fn render(&mut self) {
+        // A suboptimal frame was presented last time (e.g. after a rotation), reconfigure before acquiring another
+        if self.gpu.surface_suboptimal {
+            self.gpu.surface_suboptimal = false;
+            self.configure_surface();
+        }
+
+        if self.camera.dirty {
+            self.reconfigure_camera();
+        }
+
+        let dt = self.last_frame_time.elapsed().as_secs_f32();
+        self.last_frame_time = web_time::Instant::now();
+        self.record_frame_time(dt);
+        // Checked now and then rather than on every change, orbiting changes the camera every frame
+        if self.settings_checked_at.elapsed().as_secs_f32() >= SETTINGS_SAVE_INTERVAL {
+            self.settings_checked_at = web_time::Instant::now();
+            self.save_settings();
+        }
+        if !self.paused {
+            self.advance_animation(dt);
+        }
+        if let Some(taa) = &mut self.gpu.taa {
+            let jitter = taa.next_jitter(self.size);
+            let jittered_camera = CameraUniform {
+                view_proj: (jitter * cgmath::Matrix4::from(self.camera_uniform.view_proj)).into(),
+                ..self.camera_uniform
+            };
+            self.gpu.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[jittered_camera]));
+        }
+
+        //Create texture view. A headless state has nothing to present to
+        let Some(surface) = &self.gpu.surface else { return };
+        let output = match surface.get_current_texture() {
+            Ok(output) => {
+                self.gpu.surface_errors = 0;
+                output
+            }
+            Err(error) => {
+                self.handle_surface_error(error);
+                return;
+            }
+        };
+        if output.suboptimal {
+            platform_specific::print("Suboptimal surface texture acquired, reconfiguring on the next frame");
+            self.gpu.surface_suboptimal = true;
+        }
+        let output_texture_view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor {
+                //Without add_srgb_suffix the image we will be working with might not be "gamma
+                //correct".
+                format: Some(self.gpu.surface_format.add_srgb_suffix()),
+                ..Default::default()
+            });
+
+        // With TAA the frame is resolved into the output, with supersampling the scene is downsampled
+        // into the frame. Each renders offscreen first
+        let frame_view = self.gpu.taa.as_ref().map_or(&output_texture_view, |taa| taa.target());
+        let scene_view = self.gpu.supersample.as_ref().map_or(frame_view, |s| s.target());
+
+        //Renders the content
+        let mut encoder = self.gpu.device.create_command_encoder(&Default::default());
+        if self.compute_displacement_active() {
+            if let Some(compute) = &mut self.gpu.compute_displacement {
+                let instances = self.gpu.models.iter().map(|m| (&m.instance_buffer, m.instances.len() as u32));
+                let instance_size = std::mem::size_of::<InstanceRaw>() as u64;
+                compute.dispatch(&self.gpu.device, &mut encoder, instances, instance_size, &self.displacement_buffer);
+            }
+        }
+        // Picks up an earlier frame's timestamps, so this frame's can be resolved
+        if let Some(timer) = &mut self.gpu.timer {
+            timer.poll(&self.gpu.device);
+        }
+        let attachments = SceneAttachments {
+            msaa: self.gpu.msaa_target.as_ref(),
+            depth: self.gpu.depth_target.as_ref(),
+            shadow: self.gpu.shadow.as_ref(),
+            scissor: self.scissor_in_target(),
+            timer: self.gpu.timer.as_ref(),
+        };
+        self.render_stats = self.encode_scene(&mut encoder, scene_view, &self.camera, attachments);
+        if let Some(timer) = &self.gpu.timer {
+            timer.resolve(&mut encoder);
+        }
+
+        if let Some(supersample) = &self.gpu.supersample {
+            supersample.resolve(&mut encoder, frame_view);
+        }
+        if let Some(taa) = &mut self.gpu.taa {
+            taa.resolve(&mut encoder, &self.gpu.queue, &output_texture_view);
+        }
+
+        //Submit the command in the queue to execute
+        self.gpu.queue.submit([encoder.finish()]);
+        if let Some(timer) = &mut self.gpu.timer {
+            timer.submitted();
+        }
+        if let Some(window) = &self.window {
+            window.pre_present_notify();
+        }
+        output.present();
+    }
+}
+
+// What the scene passes draw with besides the scene's own view. Each has to match the scene view's size
+struct SceneAttachments<'a> {
+    // Drawn into then resolved into the scene view when multisampling
+    msaa: Option<&'a wgpu::TextureView>,
+    depth: Option<&'a wgpu::TextureView>,
+    shadow: Option<&'a shadow::Shadow>,
+    scissor: Option<(u32, u32, u32, u32)>,
+    // Times the main pass
+    timer: Option<&'a gpu_timer::GpuTimer>,
+}
+
+// Why the gpu couldn't be set up for a window, see State::new
+#[derive(Debug)]
+pub enum GpuError {
+    Surface(wgpu::CreateSurfaceError),
+    // No adapter can draw to the window's surface, e.g. a browser without WebGL2
+    NoAdapter,
+    Device(wgpu::RequestDeviceError),
+    // Another window's surface can't be drawn to in the format the shared pipeline draws in
+    UnsupportedFormat(wgpu::TextureFormat),
+}
+
+impl std::fmt::Display for GpuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GpuError::Surface(e) => write!(f, "Couldn't create a surface for the window: {e}"),
+            GpuError::NoAdapter => write!(f, "No adapter supports drawing to the window"),
+            GpuError::Device(e) => write!(f, "Couldn't get a device from the adapter: {e}"),
+            GpuError::UnsupportedFormat(format) => write!(f, "The window's surface doesn't support the {format:?} format"),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+// A scissor rectangle, (x, y, width, height), with no area on the surface, see State::set_scissor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScissorOutsideSurface {
+    pub rect: (u32, u32, u32, u32),
+    pub surface: winit::dpi::PhysicalSize<u32>,
+}
+
+impl std::fmt::Display for ScissorOutsideSurface {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (x, y, width, height) = self.rect;
+        write!(f, "Scissor rectangle {width}x{height} at ({x}, {y}) doesn't overlap the {}x{} surface", self.surface.width, self.surface.height)
+    }
+}
+
+impl std::error::Error for ScissorOutsideSurface {}
+
+pub struct App {
+    // One State per window, the first is created from init_content and the rest share its gpu resources
+    states: Arc<Mutex<Vec<State>>>,
+    init_content: Arc<InitContent>,
+    scenes: Vec<Scene>,
+    // The scene currently applied, scenes[0] matches init_content
+    scene_index: usize,
+}
+
+// The displacement functions in the vertex shader, see displacement_mode in shader.wgsl
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DisplacementMode {
+    // Letters are pushed away from the focus and toward the camera
+    #[default]
+    Push,
+    Swirl,
+    Wave,
+    Ripple,
+}
+
+impl DisplacementMode {
+    // The contents of displacement_mode_buffer, padded to 16 bytes for web. computed is set when the compute
+    // pass already displaced the instances
+    fn uniform(self, computed: bool) -> [u32; 4] {
+        [self as u32, computed as u32, 0, 0]
+    }
+}
+
+// The state the displacement effect starts in. The focus is in normalized device coordinates like cursor_pos
+#[derive(Debug, Copy, Clone)]
+pub struct InitialDisplacement {
+    pub focus: [f32; 2],
+    pub strength: f32,
+}
+
+impl Default for InitialDisplacement {
+    fn default() -> Self {
+        InitialDisplacement {
+            focus: [0.5, 0.5],
+            strength: 0.0,
+        }
+    }
+}
+
+// InitContent includes (effectively static) content generated during initialization
+pub struct InitContent {
+    pub alphabet_models: Vec<letters::Model>,
+    pub text: String,
+    pub layout: LayoutConfig,
+    pub initial_displacement: InitialDisplacement,
+    pub letter_texture: texture::RgbaTexture<[u8; 4]>,
+    // None binds a flat 1x1 normal texture and leaves the lighting normals alone
+    pub letter_normal_texture: Option<texture::RgbaTexture<[u8; 4]>>,
+    // Applied once the adapter is known, see State::set_anti_aliasing
+    pub anti_aliasing: AntiAliasing,
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        //Create window object
+        let window_attributes = platform_specific::window_attributes();
+        let window = Arc::new(
+            event_loop
+                .create_window(window_attributes)
+                .unwrap(),
+        );
+
+        let future = new_state(self.states.clone(), window, self.init_content.clone());
+
+        // Without a gpu there's nothing to show, desktop exits and the page keeps the message
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = pollster::block_on(future) {
+            platform_specific::show_gpu_error(&e);
+            event_loop.exit();
+        }
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = future.await {
+                platform_specific::show_gpu_error(&e);
+            }
+        });
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        use winit::event::{ElementState, MouseButton};
+
+        let mut states = match self.states.try_lock() {
+            Ok(sr) => { sr }
+            Err(_) => { return }
+        };
+        let Some(index) = states.iter().position(|s| s.window.as_ref().is_some_and(|w| w.id() == id)) else { return };
+        let state = &mut states[index];
+        match event {
+            WindowEvent::CloseRequested => {
+                println!("Closing window...");
+                states[index].save_settings();
+                states.remove(index);
+                if states.is_empty() {
+                    event_loop.exit();
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                // Ensure the surface is configured before rendering
+                if !state.gpu.surface_configured { return; }
+                state.render();
+                //Emit a new redraw requested event
+                state.request_redraw();
+            }
+            WindowEvent::Resized(size) => {
+                //Reconfigures the size of the surface.
+                //No re-render required, this event is always followed by a redraw request
+                state.resize(size);
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, inner_size_writer: _ } => {
+                // e.g. the window moved to a monitor with a different DPI. The window keeps its logical size,
+                // so its physical size scales with it
+                let logical = state.size.to_logical::<f64>(state.scale_factor);
+                state.scale_factor = scale_factor;
+                state.resize(logical.to_physical(scale_factor));
+            }
+            WindowEvent::MouseInput { device_id: _, state: mouse_state, button } => {
+                match (mouse_state, button) {
+                    (ElementState::Pressed, MouseButton::Left) => state.cursor_clicked = true,
+                    (ElementState::Released, MouseButton::Left) => state.cursor_clicked = false,
+                    _ => (),
+                };
+            }
+            WindowEvent::MouseWheel { device_id: _, delta, phase: _ } => {
+                use winit::event::MouseScrollDelta;
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+                };
+                // Scrolling up zooms in
+                state.camera.zoom(ZOOM_STEP.powf(-lines));
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                state.modifiers = modifiers.state();
+            }
+            WindowEvent::KeyboardInput { device_id: _, event, is_synthetic: _ } => {
+                use winit::keyboard::{Key, NamedKey};
+                if event.state != ElementState::Pressed {
+                    return;
+                }
+                if is_shortcut(state.modifiers) {
+                    // Ctrl+D and Ctrl+B toggle the layout debugging views
+                    match event.logical_key.as_ref() {
+                        Key::Character("d") => state.set_debug_colors(!state.debug_colors),
+                        Key::Character("b") => state.set_show_bounds(!state.show_bounds),
+                        // Ctrl+K moves the letters in a compute pass instead of the vertex shader, where supported
+                        Key::Character("k") => {
+                            let enabled = state.gpu.compute_displacement.is_none();
+                            state.set_compute_displacement(enabled);
+                        }
+                        // Ctrl+E shows the letters as wireframes, where supported
+                        Key::Character("e") => {
+                            let enabled = !state.gpu.pipeline_config.wireframe;
+                            state.set_wireframe(enabled);
+                        }
+                        // Ctrl+L outlines the letters
+                        Key::Character("l") => state.set_outline(state.gpu.outline_pipeline.is_none()),
+                        // Ctrl+O switches between perspective and orthographic
+                        Key::Character("o") => state.toggle_projection(),
+                        // Ctrl+M switches the render backend, printing the last frame's stats to compare them
+                        Key::Character("m") => {
+                            let stats = state.render_stats();
+                            let backend = match state.render_backend {
+                                RenderBackend::Instanced => RenderBackend::Merged,
+                                RenderBackend::Merged => RenderBackend::Instanced,
+                            };
+                            platform_specific::print(&format!(
+                                "{:?}: {} draw calls, {} triangles, {} buffer binds. Switching to {:?}",
+                                state.render_backend, stats.draw_calls, stats.triangles, stats.buffer_binds, backend,
+                            ));
+                            state.set_render_backend(backend);
+                        }
+                        // Ctrl+S saves what the window shows to screenshot.png
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Key::Character("s") => {
+                            let image = state.render_to_image(state.size.width, state.size.height);
+                            match image.save_png("screenshot.png") {
+                                Ok(()) => platform_specific::print("Saved screenshot.png"),
+                                Err(e) => platform_specific::print(&format!("Couldn't save screenshot.png: {e}")),
+                            }
+                        }
+                        // Ctrl+N opens another window showing the same text
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Key::Character("n") => {
+                            let window = Arc::new(event_loop.create_window(platform_specific::window_attributes()).unwrap());
+                            match state.share_with_window(window) {
+                                Ok(new_state) => {
+                                    new_state.request_redraw();
+                                    states.push(new_state);
+                                }
+                                Err(e) => platform_specific::print(&format!("Couldn't open another window: {e}")),
+                            }
+                        }
+                        _ => (),
+                    }
+                } else if event.logical_key == Key::Named(NamedKey::Space) && !state.modifiers.shift_key() {
+                    // Space pauses the animation, shift+space types a space
+                    state.set_paused(!state.paused);
+                } else if event.logical_key == Key::Named(NamedKey::Tab) {
+                    if !self.scenes.is_empty() {
+                        self.scene_index = (self.scene_index + 1) % self.scenes.len();
+                        state.apply_scene(&self.scenes[self.scene_index]);
+                    }
+                } else {
+                    state.edit_text(&event.logical_key, event.text.as_deref());
+                }
+            }
+            WindowEvent::CursorMoved { device_id: _, position } => {
+                state.update_cursor(position);
+            }
+            WindowEvent::CursorEntered { device_id: _ } => {
+                state.cursor_on_window = true;
+            }
+            WindowEvent::CursorLeft { device_id: _ } => {
+                state.cursor_on_window = false;
+            }
+            WindowEvent::Touch( t ) => {
+                use winit::event::TouchPhase;
+                match t.phase {
+                    TouchPhase::Started if !state.cursor_on_window => {
+                        state.cursor_on_window = true;
+                        state.touch_id = t.id;
+                        // Set after moving the cursor so the touch doesn't orbit from wherever the last touch ended
+                        state.update_cursor(t.location);
+                        state.cursor_clicked = true;
+                    },
+                    TouchPhase::Moved if t.id == state.touch_id => {
+                        state.update_cursor(t.location);
+                    },
+                    TouchPhase::Ended | TouchPhase::Cancelled if t.id == state.touch_id => {
+                        state.cursor_on_window = false;
+                        state.cursor_clicked = false;
+                    },
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+// A scissor rectangle given in the surface's pixels, scaled to a target of another size and clamped to fit inside it
+fn scissor_in(
+    (x, y, width, height): (u32, u32, u32, u32),
+    surface: winit::dpi::PhysicalSize<u32>,
+    target: winit::dpi::PhysicalSize<u32>,
+) -> (u32, u32, u32, u32) {
+    let scale = |v: u32, from: u32, to: u32| (v as f32 * to as f32 / from.max(1) as f32).round() as u32;
+    let (x, y) = (scale(x, surface.width, target.width).min(target.width), scale(y, surface.height, target.height).min(target.height));
+    (
+        x,
+        y,
+        scale(width, surface.width, target.width).min(target.width - x),
+        scale(height, surface.height, target.height).min(target.height - y),
+    )
+}
+
+// Keys pressed while these are held are shortcuts, not text input. Shift only changes the typed character
+fn is_shortcut(modifiers: winit::keyboard::ModifiersState) -> bool {
+    modifiers.control_key() || modifiers.alt_key() || modifiers.super_key()
+}
+
+// The size of a uniform struct, set as the min_binding_size of its layout entry so wgpu catches a
+// buffer that doesn't match the shader's struct when the bind group is created
+fn uniform_size<T>() -> Option<wgpu::BufferSize> {
+    wgpu::BufferSize::new(std::mem::size_of::<T>() as u64)
+}
+
+// Group 0: the letter texture and normal map, and their samplers
+fn create_texture_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true }
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label: Some("texture_bind_group_layout"),
+    })
+}
+
+// Group 1: the camera
+fn create_camera_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<CameraUniform>(),
+                },
+                count: None,
+            }
+        ],
+        label: Some("camera_bind_group_layout"),
+    })
+}
+
+// Group 2: the uniforms besides the camera, in the order create_misc_bind_group takes their buffers
+fn create_misc_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<DisplacementUniform>(),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<[f32; 4]>(),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<[f32; 4]>(),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<LightUniform>(),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<GradientUniform>(),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 5,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<[f32; 4]>(),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<[u32; 4]>(),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 7,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: uniform_size::<OutlineUniform>(),
+                },
+                count: None,
+            },
+        ],
+        label: Some("misc_bind_group_layout"),
+    })
+}
+
+// The buffers are in binding order: displacement, time, size, light, gradient, scroll, displacement mode, outline
+fn create_misc_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, buffers: [&wgpu::Buffer; 8]) -> wgpu::BindGroup {
+    let entries: Vec<wgpu::BindGroupEntry> = buffers.iter().enumerate().map(|(i, buffer)| wgpu::BindGroupEntry {
+        binding: i as u32,
+        resource: buffer.as_entire_binding(),
+    }).collect();
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &entries,
+        label: Some("misc_bind_group"),
+    })
+}
+
+fn create_camera_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, camera_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }
+        ],
+        label: Some("camera_bind_group"),
+    })
+}
+
+async fn new_state(states_mutex: Arc<Mutex<Vec<State>>>, window: Arc<Window>, init_content: Arc<InitContent>) -> Result<(), GpuError> {
+    let new_state = State::new(window.clone(), init_content).await?;
+    let mut states = states_mutex.lock().unwrap();
+    *states = vec![new_state];
+
+    window.request_redraw();
+    Ok(())
+}
+
+// Uploads a normal map, or a flat 1x1 one pointing straight out of the letters for None
+fn create_normal_texture(device: &wgpu::Device, queue: &wgpu::Queue, normal_map: Option<&texture::RgbaTexture<[u8; 4]>>) -> texture::GpuTexture {
+    let flat;
+    let normal_map = match normal_map {
+        Some(normal_map) => normal_map,
+        None => {
+            flat = texture::RgbaTexture::solid(1, 1, [128, 128, 255, 255], wgpu::TextureFormat::Rgba8Unorm);
+            &flat
+        }
+    };
+    texture::GpuTexture::from_rgbatexture(normal_map, device, queue, "letter_normal_texture", false, false, texture::SamplerConfig::default())
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &texture::GpuTexture,
+    normal_texture: &texture::GpuTexture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(
+        &wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&normal_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&normal_texture.sampler),
+                },
+            ],
+            label: Some("texture_bind_group"),
+        }
+    )
+}
+
+// How the letters' color is combined with what's already drawn under them
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BlendMode {
+    // The letters overwrite what's under them, the texture's alpha only ends up in the target's alpha
+    Replace,
+    // For straight alpha, where the texture's color isn't multiplied by its alpha (like the textures in
+    // texture.rs and most PNGs): color * alpha + under * (1 - alpha)
+    Alpha,
+    // For a texture whose color is already multiplied by its alpha: color + under * (1 - alpha).
+    // Straight alpha textures come out too bright at their soft edges with this
+    PremultipliedAlpha,
+}
+
+impl BlendMode {
+    fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Replace => wgpu::BlendState::REPLACE,
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::PremultipliedAlpha => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        }
+    }
+}
+
+// Options baked into the render pipeline, changing any of these requires rebuilding it
+#[derive(Debug, Copy, Clone)]
+struct PipelineConfig {
+    // Must match the sample count of the color target the pipeline draws into
+    sample_count: u32,
+    // Blended letters still write the depth, so translucent letters only show the letters behind them when those
+    // are drawn first. See State::sort_instances
+    blend_mode: BlendMode,
+    // Lets the letter texture's alpha drive MSAA coverage, anti-aliasing glyph edges without
+    // blending (so overlapping letters don't need sorting). Only takes effect when sample_count > 1
+    alpha_to_coverage: bool,
+    // Bit i enables sample i of each pixel, samples with a cleared bit are never written. Only the low
+    // sample_count bits matter, so clearing bits only thins the coverage when sample_count > 1, giving a
+    // dithered or patterned edge. wgpu's GL backend, WebGL2 included, ignores the mask and writes every sample
+    sample_mask: u64,
+    // The format of the depth attachment, None when the scene is drawn without depth testing
+    depth_format: Option<wgpu::TextureFormat>,
+    // Pulls the text toward the camera in the depth test so it wins against a background at the same depth.
+    // Only takes effect with a depth_format
+    depth_bias: wgpu::DepthBiasState,
+    // Draws only the edges of the tris. Needs Features::POLYGON_MODE_LINE, see State::set_wireframe
+    wireframe: bool,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        PipelineConfig {
+            sample_count: 1,
+            blend_mode: BlendMode::Alpha,
+            alpha_to_coverage: false,
+            sample_mask: !0,
+            depth_format: Some(wgpu::TextureFormat::Depth32Float),
+            depth_bias: wgpu::DepthBiasState::default(),
+            wireframe: false,
+        }
+    }
+}
+
+// The depth attachment for the scene target, which has to match its size and sample count
+fn create_depth_target(device: &wgpu::Device, config: &PipelineConfig, size: winit::dpi::PhysicalSize<u32>) -> Option<wgpu::TextureView> {
+    config.depth_format.map(|format| texture::GpuTexture::multisampled_target(
+        device,
+        format,
+        size.width,
+        size.height,
+        config.sample_count,
+        "depth_target",
+    ))
+}
+
+// outline creates the pipeline that draws the letters' outlines instead, see vs_outline in shader.wgsl.
+// The outline is drawn first and blended under the letters without writing the depth, so they always cover it
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    surface_format: wgpu::TextureFormat,
+    config: &PipelineConfig,
+    outline: bool,
+) -> wgpu::RenderPipeline {
+    let (label, vs_entry_point, fs_entry_point) = if outline {
+        ("outline_pipeline", "vs_outline", "fs_outline")
+    } else {
+        ("render_pipeline", "vs_main", "fs_main")
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some(vs_entry_point),
+            buffers: &[
+                letters::desc(),
+                InstanceRaw::desc(),
+            ],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(fs_entry_point),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: surface_format.add_srgb_suffix(),
+                blend: Some(if outline { wgpu::BlendState::ALPHA_BLENDING } else { config.blend_mode.blend_state() }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            // Line requires Features::POLYGON_MODE_LINE
+            polygon_mode: if config.wireframe { wgpu::PolygonMode::Line } else { wgpu::PolygonMode::Fill },
+            // Requres Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requres Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: depth_stencil_state(config, outline),
+        multisample: wgpu::MultisampleState {
+            count: config.sample_count,
+            mask: config.sample_mask,
+            // The outline's color has no texture alpha to turn into coverage
+            alpha_to_coverage_enabled: config.alpha_to_coverage && config.sample_count > 1 && !outline,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+// The depth test of the letter pipeline, or of the outline pipeline when outline is set
+fn depth_stencil_state(config: &PipelineConfig, outline: bool) -> Option<wgpu::DepthStencilState> {
+    config.depth_format.map(|format| wgpu::DepthStencilState {
+        format,
+        depth_write_enabled: !outline,
+        depth_compare: if outline { wgpu::CompareFunction::Always } else { wgpu::CompareFunction::Less },
+        stencil: wgpu::StencilState::default(),
+        bias: config.depth_bias,
+    })
+}
+
+// Overlays are drawn this far in front of the text's plane, so they pass the depth test over the letters
+const OVERLAY_Z_OFFSET: f32 = 0.001;
+
+// Builds the underline and strikethrough bars spanning each line of the laid out letters
+fn create_decoration_model(device: &wgpu::Device, models: &[Model], layout: &LayoutConfig, decoration: &TextDecoration) -> Option<Model> {
+    let mesh = decoration_mesh(models.iter().flat_map(|m| &m.instances), layout, decoration)?;
+    Some(create_overlay_model(device, &mesh, WHITE, "decoration"))
+}
+
+// The bars of create_decoration_model, None when there are no bars to draw
+fn decoration_mesh<'a>(instances: impl Iterator<Item = &'a Instance>, layout: &LayoutConfig, decoration: &TextDecoration) -> Option<letters::Model> {
+    // Every letter on a line shares the same y and scale. Find the horizontal extent of each line
+    let mut lines: Vec<(f32, f32, f32, f32)> = vec![]; // (y, scale, left, right)
+    for instance in instances {
+        let (y, scale) = (instance.position.y, instance.scale);
+        let left = instance.position.x - 0.5 * scale;
+        let right = instance.position.x + 0.5 * scale;
+        match lines.iter_mut().find(|l| l.0 == y && l.1 == scale) {
+            Some(line) => {
+                line.2 = f32::min(line.2, left);
+                line.3 = f32::max(line.3, right);
+            }
+            None => lines.push((y, scale, left, right)),
+        }
+    }
+
+    let z = layout.z_plane + OVERLAY_Z_OFFSET;
+    let mut bars = vec![];
+    for &(y, scale, left, right) in &lines {
+        let thickness = decoration.thickness * scale;
+        // Letters sit on their baseline at y, and are scale tall
+        if decoration.underline {
+            bars.push(letters::Model::bar(left, right, y - 1.5 * thickness, y - 0.5 * thickness, z, 0.0));
+        }
+        if decoration.strikethrough {
+            let middle = y + 0.5 * scale;
+            bars.push(letters::Model::bar(left, right, middle - 0.5 * thickness, middle + 0.5 * thickness, z, 0.5));
+        }
+    }
+    bars.into_iter().reduce(letters::Model::append)
+}
+
+// Builds outlines around the bounds of every letter instance, for debugging the layout
+fn create_bounds_model(device: &wgpu::Device, models: &[Model], glyph_bounds: &[Option<letters::Bounds>], z_plane: f32) -> Option<Model> {
+    let mut boxes = vec![];
+    for (model, bounds) in models.iter().zip(glyph_bounds) {
+        let Some(bounds) = bounds else { continue };
+        for instance in &model.instances {
+            let (x, y, scale) = (instance.position.x, instance.position.y, instance.scale);
+            let (left, right) = (x + scale * bounds.min[0], x + scale * bounds.max[0]);
+            let (bottom, top) = (y + scale * bounds.min[1], y + scale * bounds.max[1]);
+            let t = 0.02 * scale;
+            boxes.push(
+                letters::Model::bar(left, right, bottom, bottom + t, z_plane, 0.0)
+                    .append(letters::Model::bar(left, right, top - t, top, z_plane, 0.0))
+                    .append(letters::Model::bar(left, left + t, bottom, top, z_plane, 0.0))
+                    .append(letters::Model::bar(right - t, right, bottom, top, z_plane, 0.0))
+            );
+        }
+    }
+    let mesh = boxes.into_iter().reduce(letters::Model::append)?;
+    Some(create_overlay_model(device, &mesh, [1.0, 0.0, 0.0, 1.0], "bounds"))
+}
+
+// Uploads a mesh that's already in world space, drawn with a single untransformed instance
+fn create_overlay_model(device: &wgpu::Device, mesh: &letters::Model, color: [f32; 4], label: &str) -> Model {
+    let identity = Instance {
+        position: cgmath::Vector3::zero(),
+        rotation: cgmath::Quaternion::one(),
+        scale: 1.0,
+        color,
+    };
+    Model {
+        instance_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&(label.to_string() + "_instance_buffer")),
+            contents: bytemuck::cast_slice(&[identity.to_raw()]),
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+        instances: vec![identity],
+        vertex_data: VertexData {
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&(label.to_string() + "_vertex_buffer")),
+                contents: bytemuck::cast_slice(&mesh.verts),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&(label.to_string() + "_index_buffer")),
+                contents: bytemuck::cast_slice(&mesh.tri_idxs),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            num_indices: mesh.number_indices(),
+        },
+        texture: None,
+    }
+}
+
+fn create_models(device: &wgpu::Device, text: &str, layout: &LayoutConfig, glyph_map: &GlyphMap, alphabet_models: &[letters::Model]) -> [Model; letters::GLYPH_COUNT] {
+    // Load the alphabet models into buffers
+    let vertex_data: [VertexData; letters::GLYPH_COUNT] = alphabet_models.iter().map(
+        |letter|
+        VertexData {
+            vertex_buffer: device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor{
+                    label: Some("vertex_buffer"),
+                    contents: bytemuck::cast_slice(&letter.verts),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }),
+            index_buffer: device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor{
+                    label: Some("index_buffer"),
+                    contents: bytemuck::cast_slice(&letter.tri_idxs),
+                    usage: wgpu::BufferUsages::INDEX,
+                }),
+            num_indices: letter.number_indices(),
+        }
+    ).collect::<Vec<_>>().try_into().unwrap();
+
+    // Get the required instances from the text display
+    let instances_list = layout_instances(text, layout, glyph_map);
+
+    let instance_buffers: [wgpu::Buffer; letters::GLYPH_COUNT] = instances_list.iter().enumerate().map(
+        |(i, instances)| create_instance_buffer(device, instances, i)
+    ).collect::<Vec<_>>().try_into().unwrap();
+
+    instances_list.into_iter()
+        .zip(instance_buffers)
+        .zip(vertex_data)
+        .map(
+            |((instances, instance_buffer), vertex_data)| {
+                Model {
+                    instances,
+                    instance_buffer,
+                    vertex_data,
+                    texture: None,
+                }
+            }
+    ).collect::<Vec<_>>().try_into().unwrap()
+}
+
+// The instances of each glyph for the text, deduplicated if the layout asks for it
+fn layout_instances(text: &str, layout: &LayoutConfig, glyph_map: &GlyphMap) -> [Vec<Instance>; letters::GLYPH_COUNT] {
+    let instances_list = get_letter_instances(text, layout, glyph_map);
+    if layout.dedup_instances {
+        instances_list.map(dedup_instances)
+    } else {
+        instances_list
+    }
+}
+
+// A buffer holding the instances of the glyph with this index
+fn create_instance_buffer(device: &wgpu::Device, instances: &[Instance], index: usize) -> wgpu::Buffer {
+    let instance_data: Vec<InstanceRaw> = instances.iter().map(Instance::to_raw).collect();
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(&("instance_buffer index: ".to_string() + &index.to_string())),
+        contents: bytemuck::cast_slice(&instance_data),
+        usage: displace::instance_buffer_usage(device),
+    })
+}
+
+// Replaces a model's instances, writing over its instance buffer if they fit and allocating a bigger one if not.
+// The buffer is never shrunk, so text that shrinks and grows back again doesn't allocate
+fn upload_instances(device: &wgpu::Device, queue: &wgpu::Queue, model: &mut Model, instances: Vec<Instance>, index: usize) {
+    let instance_data: Vec<InstanceRaw> = instances.iter().map(Instance::to_raw).collect();
+    let size = std::mem::size_of_val(instance_data.as_slice()) as wgpu::BufferAddress;
+    if size > model.instance_buffer.size() {
+        model.instance_buffer = create_instance_buffer(device, &instances, index);
+    } else if size > 0 {
+        queue.write_buffer(&model.instance_buffer, 0, bytemuck::cast_slice(&instance_data));
+    }
+    model.instances = instances;
+}
+
+// Lays text out into the instances of each glyph, with one line per line of text (after wrapping when the layout
+// asks for it). Lines are placed between x=[-10, 10] by the layout's alignment: centered at a fixed advance,
+// stretched to fill the bounds, or justified. Each letter's height matches its width times scale_ratio.
+// The block is centered vertically by its glyphs' extents, from the top of the first line's letters to the
+// bottom of the last line's, rather than by the instance positions, which are the bottoms of the letters.
+// Chars the glyph map has no glyph for, like spaces and punctuation, only take up room. Instances are at
+// z=layout.z_plane, and moved so their average is layout.center when that's set
+fn get_letter_instances(text: &str, layout: &LayoutConfig, glyph_map: &GlyphMap) -> [Vec<Instance>; letters::GLYPH_COUNT] {
+    let mut letter_instances: [Vec<Instance>; letters::GLYPH_COUNT] = std::array::from_fn(|_| Vec::new());
+
+    let lines = layout_lines(text, layout);
+    // The gutter fits the digits of the last line number and a space before the text
+    let gutter_digits = lines.len().to_string().len();
+    let left = if layout.line_numbers {
+        LEFT_BOUND + (gutter_digits + 1) as f32 * layout.advance
+    } else {
+        LEFT_BOUND
+    };
+    // Each line is as tall as its letters are wide
+    let mut laid_out_lines = vec![];
+    for (line_index, s) in lines.iter().enumerate() {
+        // Each letter in the line and the x of its center
+        let (width_per_character, letters) = match layout.alignment {
+            // An empty line has nothing to stretch, it's as tall as a centered one
+            Alignment::Stretch if s.is_empty() => (layout.advance, vec![]),
+            Alignment::Stretch => {
+                // Count chars rather than bytes, multi-byte chars still take up a single letter's width
+                let num_chars = s.chars().count();
+                // The letters and the tracking between them fill the line
+                let width_per_character = ((RIGHT_BOUND - left - layout.tracking * (num_chars as f32 - 1.0)) / num_chars as f32).max(0.0);
+                let step = (width_per_character + layout.tracking).max(0.0);
+                let start = 0.5 * (left + RIGHT_BOUND - num_chars as f32 * step);
+                let letters: Vec<(char, f32)> = s.chars().enumerate().map(
+                    |(i, c)| (c, start + (i as f32 + 0.5) * step)
+                ).collect();
+                (width_per_character, letters)
+            }
+            Alignment::Justify => {
+                // The last line of a paragraph isn't spread out
+                let end_of_paragraph = lines.get(line_index + 1).is_none_or(|next| next.trim().is_empty());
+                (layout.advance, justify_line(s, left, layout.letter_step(), !end_of_paragraph))
+            }
+            Alignment::Center => {
+                let step = layout.letter_step();
+                let start = 0.5 * (left + RIGHT_BOUND - s.chars().count() as f32 * step);
+                let letters = s.chars().enumerate().map(
+                    |(i, c)| (c, start + (i as f32 + 0.5) * step)
+                ).collect();
+                (layout.advance, letters)
+            }
+        };
+        laid_out_lines.push((line_index, width_per_character, letters));
+    }
+
+    // Center the block vertically on y = 0, from the top of the first line's letters to the bottom of the last line
+    let total_height: f32 = laid_out_lines.iter().map(|l| l.1).sum();
+    // The gap above the first line's letters, since the letters are only scale_ratio of the line height
+    let first_gap = laid_out_lines.first().map_or(0.0, |l| l.1 * (1.0 - layout.scale_ratio));
+    let mut y = 0.5 * (total_height + first_gap);
+
+    for (line_index, width_per_character, letters) in laid_out_lines {
+        let scale = width_per_character * layout.scale_ratio;
+
+        y -= width_per_character;
+
+        // Line numbers are drawn at the size of the text's letters when they fit the gutter's advance
+        let mut digits = vec![];
+        if layout.line_numbers {
+            let number = (line_index + 1).to_string();
+            let first_column = gutter_digits - number.len();
+            digits = number.chars().enumerate().map(
+                |(i, c)| (c, LEFT_BOUND + (first_column + i) as f32 * layout.advance + 0.5 * layout.advance)
+            ).collect();
+        }
+
+        let digit_scale = scale.min(layout.advance * layout.scale_ratio);
+        let glyphs = letters.into_iter().map(|(c, x)| (c, x, scale))
+            .chain(digits.into_iter().map(|(c, x)| (c, x, digit_scale)));
+        for (c, x, scale) in glyphs {
+            // Spaces, and chars without a glyph like punctuation, only take up room
+            let Some(idx) = glyph_map.glyph_index(c) else {
+                continue;
+            };
+            let position = cgmath::Vector3 { x, y, z: layout.z_plane };
+            let rotation = if position.is_zero() {
+                cgmath::Quaternion::from_axis_angle(cgmath::Vector3::unit_z(), cgmath::Deg(0.0))
+            } else {
+                cgmath::Quaternion::from_axis_angle(position.normalize(), cgmath::Deg(0.0))
+            };
+            letter_instances[idx].push( Instance {
+                position, rotation, scale, color: WHITE,
+            });
+        }
+    }
+
+    if let Some(center) = layout.center {
+        let count = letter_instances.iter().map(Vec::len).sum::<usize>();
+        if count > 0 {
+            let sum = letter_instances.iter().flatten()
+                .fold(cgmath::Vector3::zero(), |sum, instance| sum + instance.position);
+            let average = sum / count as f32;
+            let offset = cgmath::Vector3::new(center[0] - average.x, center[1] - average.y, 0.0);
+            for instance in letter_instances.iter_mut().flatten() {
+                instance.position += offset;
+            }
+        }
+    }
+    color_instances(letter_instances.iter_mut().flatten().collect(), layout.color_gradient);
+
+    letter_instances
+}
+
+// The world-space extents of text laid out by get_letter_instances, glyph_bounds are the bounds of each glyph model
+fn measure_text(text: &str, layout: &LayoutConfig, glyph_map: &GlyphMap, glyph_bounds: &[Option<letters::Bounds>]) -> TextMetrics {
+    let mut min = [f32::MAX, f32::MAX];
+    let mut max = [f32::MIN, f32::MIN];
+    let mut baseline = f32::MIN;
+    for (instances, bounds) in get_letter_instances(text, layout, glyph_map).iter().zip(glyph_bounds) {
+        // Letters without geometry don't take up any space
+        let Some(bounds) = bounds else { continue };
+        for instance in instances {
+            for i in 0..2 {
+                min[i] = f32::min(min[i], instance.position[i] + instance.scale * bounds.min[i]);
+                max[i] = f32::max(max[i], instance.position[i] + instance.scale * bounds.max[i]);
+            }
+            baseline = f32::max(baseline, instance.position.y);
+        }
+    }
+
+    let line_count = layout_lines(text, layout).len();
+    if baseline == f32::MIN {
+        return TextMetrics { width: 0.0, height: 0.0, line_count, baseline: 0.0 };
+    }
+    TextMetrics {
+        width: max[0] - min[0],
+        height: max[1] - min[1],
+        line_count,
+        baseline,
+    }
+}
+
+// Tints every instance with the gradient's color at its position, or white without a gradient.
+// The gradient spans from the first to the last instance along its axis, when they're all level with each
+// other (like a single letter) they all get the start color
+fn color_instances(mut instances: Vec<&mut Instance>, gradient: Option<TextGradient>) {
+    let Some(gradient) = gradient else {
+        for instance in instances {
+            instance.color = WHITE;
+        }
+        return;
+    };
+    // Vertical gradients start at the top, where y is largest
+    let along = |instance: &Instance| match gradient.axis {
+        GradientAxis::Horizontal => instance.position.x,
+        GradientAxis::Vertical => -instance.position.y,
+    };
+    let (min, max) = instances.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), instance| {
+        (min.min(along(instance)), max.max(along(instance)))
+    });
+    let range = max - min;
+    for instance in &mut instances {
+        let t = if range > INSTANCE_EPSILON { (along(instance) - min) / range } else { 0.0 };
+        instance.color = std::array::from_fn(|i| gradient.start[i] + t * (gradient.end[i] - gradient.start[i]));
+    }
+}
+
+// The lines text is laid out in, wrapped when the layout asks for it
+fn layout_lines(text: &str, layout: &LayoutConfig) -> Vec<String> {
+    match layout.wrap {
+        Some(max_chars) => wrap_lines(text, max_chars),
+        None => text.lines().map(str::to_string).collect(),
+    }
+}
+
+// Breaks lines longer than max_chars between words, words longer than a whole line are broken wherever
+// they reach max_chars. Lines that already fit are kept as written, spaces included
+fn wrap_lines(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut wrapped = vec![];
+    for line in text.lines() {
+        if line.chars().count() <= max_chars {
+            wrapped.push(line.to_string());
+            continue;
+        }
+        let mut current: Vec<char> = vec![];
+        for word in line.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            if !current.is_empty() && current.len() + 1 + word.len() <= max_chars {
+                current.push(' ');
+                current.extend(word);
+                continue;
+            }
+            if !current.is_empty() {
+                wrapped.push(current.drain(..).collect());
+            }
+            while word.len() > max_chars {
+                wrapped.push(word.drain(..max_chars).collect());
+            }
+            current = word;
+        }
+        wrapped.push(current.into_iter().collect());
+    }
+    wrapped
+}
+
+// Lays out the words of a line at a fixed advance, starting from left.
+// When spread is set, the gaps between words grow so the line ends at RIGHT_BOUND
+fn justify_line(line: &str, left: f32, advance: f32, spread: bool) -> Vec<(char, f32)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let letter_count: usize = words.iter().map(|w| w.chars().count()).sum();
+    let gap = if spread && words.len() > 1 {
+        // Lines that are already too long keep a regular space
+        f32::max(advance, (RIGHT_BOUND - left - letter_count as f32 * advance) / (words.len() - 1) as f32)
+    } else {
+        advance
+    };
+
+    let mut letters = vec![];
+    let mut x = left;
+    for word in words {
+        for c in word.chars() {
+            letters.push((c, x + 0.5 * advance));
+            x += advance;
+        }
+        x += gap;
+    }
+    letters
+}
+
+// Routes chars to glyph models, chars without an override use letter_index
+#[derive(Debug, Clone, Default)]
+pub struct GlyphMap {
+    overrides: std::collections::HashMap<char, usize>,
+}
+
+impl GlyphMap {
+    pub fn with(mut self, c: char, glyph_index: usize) -> Self {
+        self.overrides.insert(c, glyph_index);
+        self
+    }
+
+    // None for chars there's no glyph for
+    pub fn glyph_index(&self, c: char) -> Option<usize> {
+        self.overrides.get(&c).copied().or_else(|| letter_index(c))
+    }
+
+    // The char a glyph is drawn for, the inverse of glyph_index. Overrides routed to the glyph win, the first
+    // in char order, over the letter or digit it has by default
+    pub fn glyph_char(&self, glyph_index: usize) -> Option<char> {
+        self.overrides.iter().filter(|&(_, &i)| i == glyph_index).map(|(&c, _)| c).min()
+            .or_else(|| letter_char(glyph_index).filter(|&c| self.glyph_index(c) == Some(glyph_index)))
+    }
+}
+
+// The index of a letter or digit's glyph, upper and lower case share one. None for anything else
+fn letter_index(c: char) -> Option<usize> {
+    if c.is_ascii_alphabetic() {
+        Some(c.to_ascii_lowercase() as usize - 'a' as usize)
+    } else {
+        c.to_digit(10).map(|d| 26 + d as usize)
+    }
+}
+
+// The lowercase letter or digit whose glyph is at an index, the inverse of letter_index
+fn letter_char(index: usize) -> Option<char> {
+    match index {
+        0..26 => Some((b'a' + index as u8) as char),
+        _ => char::from_digit(index as u32 - 26, 10),
+    }
+}
+
+// The number of lines in the .rs files under src/, counted by the build.rs script
+fn source_line_count() -> u32 {
+    include!(concat!(env!("OUT_DIR"), "/linecount.txt"))
+}
+
+impl InitContent {
+    // The demo's content, the built-in glyphs showing "hello world" with the pixelated letter texture
+    pub fn demo() -> Self {
+        InitContent {
+            alphabet_models: letters::create_alphabet_models(letters::DEFAULT_STROKE_WEIGHT),
+            text: "hello\nworld".to_string(),
+            layout: LayoutConfig::default(),
+            initial_displacement: InitialDisplacement::default(),
+            letter_texture: letters::create_pixelated_letter_texture(),
+            letter_normal_texture: None,
+            // The hard-edged glyph triangles look jagged without it
+            anti_aliasing: AntiAliasing::Msaa(4),
+        }
+    }
+}
+
+impl App {
+    // The windows are created from init_content once the event loop starts, see run
+    pub fn new(init_content: InitContent) -> Self {
+        // Letters without a glyph yet are laid out but draw nothing
+        let implemented = letters::implemented_glyphs();
+        let mut missing: Vec<char> = init_content.text.chars()
+            .filter(|c| letter_index(*c).is_some_and(|i| !implemented[i]))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        missing.sort();
+        missing.dedup();
+        if !missing.is_empty() {
+            platform_specific::print(&format!("Warning: no glyph yet for {:?}, they will be blank", missing));
+        }
+
+        App {
+            states: Arc::new(Mutex::new(vec![])),
+            scenes: demo_scenes(&init_content.text),
+            scene_index: 0,
+            init_content: Arc::new(init_content),
+        }
+    }
+}
+
+// Runs the app until its windows are closed. On wasm this returns right away and the browser drives the app
+pub fn run(app: App) -> Result<(), winit::error::EventLoopError> {
+    let event_loop = EventLoop::new().unwrap();
+    event_loop.set_control_flow(ControlFlow::Wait); // This seems to fix a winit-related performance problem I have on the web???
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut app = app;
+        platform_specific::print(&format!("Rendering with {} lines of Rust", source_line_count()));
+        event_loop.run_app(&mut app)?;
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wgpu::web_sys;
+        use web_sys::wasm_bindgen::JsCast;
+
+        let linecount_heading = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("linecount_element"))
+            .map(|e| e.dyn_into::<web_sys::HtmlElement>().unwrap())
+            .unwrap();
+        let s = linecount_heading.inner_text().replace("____", &source_line_count().to_string());
+        linecount_heading.set_inner_text(&s);
+
+        //Spawn_app is similar to run_app, but preferred for wasm since it does not require using
+        //exceptions for control flow and cluttering the web console
+        use winit::platform::web::EventLoopExtWebSys;
+        event_loop.spawn_app(app); 
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    // A device for the tests that need a gpu, None (and the test is skipped) when there's no adapter
+    pub(crate) fn test_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let Some(adapter) = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())) else {
+            eprintln!("No adapter, skipping the test");
+            return None;
+        };
+        let descriptor = platform_specific::device_descriptor(adapter.features());
+        pollster::block_on(adapter.request_device(&descriptor, None)).ok()
+    }
+
+    // The layout and shader State::new builds its pipelines with
+    fn test_pipeline_layout(device: &wgpu::Device) -> (wgpu::PipelineLayout, wgpu::ShaderModule) {
+        let layouts = [
+            create_texture_bind_group_layout(device),
+            create_camera_bind_group_layout(device),
+            create_misc_bind_group_layout(device),
+        ];
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &layouts.iter().collect::<Vec<_>>(),
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
+        });
+        (layout, shader)
+    }
+
+    // A headless state showing text, None (and the test is skipped) when there's no adapter
+    pub(crate) fn test_state(text: &str, width: u32, height: u32) -> Option<State> {
+        let init_content = InitContent { text: text.to_string(), anti_aliasing: AntiAliasing::None, ..InitContent::demo() };
+        let size = winit::dpi::PhysicalSize::new(width, height);
+        match pollster::block_on(State::new_headless(size, Arc::new(init_content))) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                eprintln!("{e}, skipping the test");
+                None
+            }
+        }
+    }
+
+    // How many pixels in the columns aren't the transparent clear color
+    fn drawn_pixels(image: &texture::RgbaTexture<[u8; 4]>, columns: std::ops::Range<u32>) -> usize {
+        columns.flat_map(|x| (0..image.height).map(move |y| image.get_pixel(x, y)))
+            .filter(|pixel| pixel[3] != 0)
+            .count()
+    }
+
+    fn default_glyph_bounds() -> Vec<Option<letters::Bounds>> {
+        letters::create_alphabet_models(letters::DEFAULT_STROKE_WEIGHT).iter().map(letters::Model::bounds).collect()
+    }
+
+    #[test]
+    fn measure_text_single_line() {
+        let metrics = measure_text("hi", &LayoutConfig::default(), &GlyphMap::default(), &default_glyph_bounds());
+        // h spans x=[-0.5,0.5] and i x=[-0.3,0.3], centered at x=-1 and x=1 at scale 1.5
+        assert_close(metrics.width, 3.2);
+        assert_close(metrics.height, 1.5);
+        assert_close(metrics.baseline, -0.75);
+        assert_eq!(metrics.line_count, 1);
+    }
+
+    #[test]
+    fn measure_text_two_lines() {
+        let metrics = measure_text("hi\nhi", &LayoutConfig::default(), &GlyphMap::default(), &default_glyph_bounds());
+        // The second line sits a line height (the advance) below the first
+        assert_close(metrics.width, 3.2);
+        assert_close(metrics.height, 3.5);
+        assert_close(metrics.baseline, 0.25);
+        assert_eq!(metrics.line_count, 2);
+    }
+
+    #[test]
+    fn blocks_at_different_z_planes() {
+        for z_plane in [-5.0, 2.5] {
+            let layout = LayoutConfig { z_plane, ..Default::default() };
+            let instances = get_letter_instances("ab\ncd", &layout, &GlyphMap::default());
+            assert_eq!(instances.iter().flatten().count(), 4);
+            for instance in instances.iter().flatten() {
+                assert_eq!(instance.position.z, z_plane);
+            }
+        }
+    }
+
+    #[test]
+    fn alpha_to_coverage_pipeline_builds() {
+        let Some((device, _queue)) = test_device() else { return };
+        let (layout, shader) = test_pipeline_layout(&device);
+        let config = PipelineConfig { sample_count: 4, alpha_to_coverage: true, ..Default::default() };
+        // wgpu panics on a validation error
+        create_render_pipeline(&device, &layout, &shader, wgpu::TextureFormat::Rgba8UnormSrgb, &config, false);
+    }
+
+    #[test]
+    fn source_line_count_is_positive() {
+        assert!(source_line_count() > 0);
+    }
+
+    #[test]
+    fn ray_through_screen_center_points_forward() {
+        let camera = Camera::new_default(2.0);
+        let (origin, direction) = camera.ray_from_screen(0.0, 0.0);
+        let forward = (camera.target - camera.eye).normalize();
+        assert!(direction.dot(forward) > 0.9999, "{direction:?}");
+        // The origin is on the near plane, straight in front of the eye
+        assert!((origin - camera.eye).normalize().dot(forward) > 0.9999);
+        assert_close((origin - camera.eye).magnitude(), camera.znear);
+    }
+
+    #[test]
+    fn initial_displacement_buffer_contents() {
+        let camera = Camera::new_default(4.0);
+        let (_, inverse_view_proj) = camera.create_matrices();
+        let initial = InitialDisplacement { focus: [0.5, -0.25], strength: 0.3 };
+        let uniform = DisplacementUniform::initial(initial, -2.0, inverse_view_proj);
+        assert_eq!(uniform.count, 1);
+        // The point is on the text's plane, under the focus on screen
+        let [x, y, z, strength] = uniform.points[0];
+        assert_close(z, -2.0);
+        assert_close(strength, 0.3);
+        let clip = camera.build_view_projection_matrix() * cgmath::Vector4::new(x, y, z, 1.0);
+        assert_close(clip.x / clip.w, 0.5);
+        assert_close(clip.y / clip.w, -0.25);
+        // The buffer starts with the point, the count follows the array
+        let bytes = bytemuck::bytes_of(&uniform);
+        assert_eq!(&bytes[..16], bytemuck::cast_slice::<f32, u8>(&uniform.points[0]));
+        assert_eq!(&bytes[16 * MAX_FOCUS_POINTS..16 * MAX_FOCUS_POINTS + 4], &1u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn gradient_runs_from_bottom_to_top_of_each_letter() {
+        // fs_main mixes from bottom to top by tex_coords.y, which is 0 at a letter's bottom and 1 at its top
+        for model in letters::create_alphabet_models(letters::DEFAULT_STROKE_WEIGHT) {
+            for vert in &model.verts {
+                let (y, tex_y) = (vert.position()[1], vert.tex_coords()[1]);
+                assert_close(tex_y, y.clamp(0.0, 1.0));
+            }
+        }
+        // The uniform matches the shader's Gradient struct: top, then bottom, then enabled
+        let uniform = GradientUniform::new([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0], true);
+        let floats: &[f32] = bytemuck::cast_slice(bytemuck::bytes_of(&uniform));
+        assert_eq!(&floats[0..4], &[1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(&floats[4..8], &[0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(bytemuck::cast_slice::<f32, u32>(&floats[8..9]), &[1]);
+        assert_eq!(std::mem::size_of::<GradientUniform>(), 48);
+    }
+
+    #[test]
+    fn model_with_its_own_texture_uses_its_own_bind_group() {
+        let Some((device, queue)) = test_device() else { return };
+        let layout = create_texture_bind_group_layout(&device);
+        let normal_texture = create_normal_texture(&device, &queue, None);
+        let letter_texture = texture::GpuTexture::from_rgbatexture(
+            &texture::RgbaTexture::solid(4, 4, [255u8; 4], wgpu::TextureFormat::Rgba8UnormSrgb),
+            &device, &queue, "letter_texture", false, false, texture::SamplerConfig::default(),
+        );
+        let shared = create_texture_bind_group(&device, &layout, &letter_texture, &normal_texture);
+
+        let alphabet = letters::create_alphabet_models(letters::DEFAULT_STROKE_WEIGHT);
+        let mut models = create_models(&device, "ab", &LayoutConfig::default(), &GlyphMap::default(), &alphabet);
+        let red = texture::RgbaTexture::solid(4, 4, [255, 0, 0, 255], wgpu::TextureFormat::Rgba8UnormSrgb);
+        models[0].texture = Some(create_model_texture(&device, &queue, &layout, &red, &normal_texture, 0));
+
+        assert_ne!(model_texture_bind_group(&models[0], &shared), &shared);
+        assert_eq!(model_texture_bind_group(&models[1], &shared), &shared);
+    }
+
+    #[test]
+    fn justified_word_gap_fills_the_line() {
+        let spread = justify_line("ab cd", LEFT_BOUND, 2.0, true);
+        let regular = justify_line("ab cd", LEFT_BOUND, 2.0, false);
+        // 4 letters take up 8.0 of the 20.0 wide line, the single gap gets the other 12.0
+        assert_close(regular[2].1 - regular[1].1, 4.0);
+        assert_close(spread[2].1 - spread[1].1, 14.0);
+        // The last letter ends at the right bound
+        assert_close(spread[3].1 + 1.0, RIGHT_BOUND);
+        assert_close(spread[0].1, regular[0].1);
+    }
+
+    #[test]
+    fn decoration_bars_sit_under_and_through_a_line() {
+        let layout = LayoutConfig::default();
+        let instances = get_letter_instances("hi", &layout, &GlyphMap::default());
+        // The letters' baseline is at y = -0.75 and they're 1.5 tall
+        let y_range = |decoration: TextDecoration| {
+            let mesh = decoration_mesh(instances.iter().flatten(), &layout, &decoration).unwrap();
+            let ys: Vec<f32> = mesh.verts.iter().map(|v| v.position()[1]).collect();
+            let xs: Vec<f32> = mesh.verts.iter().map(|v| v.position()[0]).collect();
+            assert_close(xs.iter().copied().fold(f32::MAX, f32::min), -1.75);
+            assert_close(xs.iter().copied().fold(f32::MIN, f32::max), 1.75);
+            (ys.iter().copied().fold(f32::MAX, f32::min), ys.iter().copied().fold(f32::MIN, f32::max))
+        };
+        let thickness = 0.08 * 1.5;
+        let (bottom, top) = y_range(TextDecoration { underline: true, ..Default::default() });
+        assert_close(bottom, -0.75 - 1.5 * thickness);
+        assert_close(top, -0.75 - 0.5 * thickness);
+        let (bottom, top) = y_range(TextDecoration { strikethrough: true, ..Default::default() });
+        assert_close(bottom, -0.5 * thickness);
+        assert_close(top, 0.5 * thickness);
+        assert!(decoration_mesh(instances.iter().flatten(), &layout, &TextDecoration::default()).is_none());
+    }
+
+    #[test]
+    fn scissor_is_scaled_and_clamped_to_the_target() {
+        let size = |width, height| winit::dpi::PhysicalSize::new(width, height);
+        assert_eq!(scissor_in((10, 0, 50, 100), size(100, 100), size(200, 100)), (20, 0, 100, 100));
+        assert_eq!(scissor_in((80, 90, 50, 50), size(100, 100), size(100, 100)), (80, 90, 20, 10));
+        assert_eq!(scissor_in((150, 0, 10, 10), size(100, 100), size(100, 100)), (100, 0, 0, 10));
+    }
+
+    #[test]
+    fn scissor_clips_the_right_half() {
+        let Some(mut state) = test_state("hello\nworld", 64, 32) else { return };
+        let full = state.render_to_image(64, 32);
+        assert!(drawn_pixels(&full, 0..32) > 0 && drawn_pixels(&full, 32..64) > 0);
+        state.set_scissor(Some((0, 0, 32, 32))).unwrap();
+        let clipped = state.render_to_image(64, 32);
+        assert_eq!(drawn_pixels(&clipped, 32..64), 0);
+        assert_eq!(drawn_pixels(&clipped, 0..32), drawn_pixels(&full, 0..32));
+    }
+
+    #[test]
+    fn letters_get_distinct_debug_colors() {
+        for i in 0..letters::GLYPH_COUNT {
+            for j in 0..i {
+                assert_ne!(debug_color(i), debug_color(j), "glyphs {i} and {j}");
+            }
+        }
+        let Some(mut state) = test_state("he", 64, 32) else { return };
+        state.set_debug_colors(true);
+        let color = |c: char| state.gpu.models[letter_index(c).unwrap()].instances[0].color;
+        assert_eq!(color('h'), debug_color(letter_index('h').unwrap()));
+        assert_ne!(color('h'), color('e'));
+    }
+
+    #[test]
+    fn scroll_offset_advances_by_speed_times_dt() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        state.set_marquee(2.0);
+        for frame in 1..=3 {
+            state.advance_scroll(0.1);
+            assert_close(state.scroll_offset, 0.2 * frame as f32);
+        }
+        // Past the wrap width it starts over
+        let wrap_width = RIGHT_BOUND - LEFT_BOUND + 2.0 * MARQUEE_MARGIN;
+        state.advance_scroll(wrap_width / 2.0);
+        assert_close(state.scroll_offset, 0.6);
+        state.set_marquee(0.0);
+        assert_eq!(state.scroll_offset, 0.0);
+    }
+
+    #[test]
+    fn changing_fovy_keeps_the_view_matrix() {
+        let mut camera = Camera::new_default(2.0);
+        camera.orbit(0.3, 0.2);
+        camera.dirty = false;
+        let view: [[f32; 4]; 4] = camera.view.into();
+        let projection = camera.build_projection_matrix();
+        camera.set_projection(Projection::Perspective { fovy: 30.0 });
+        camera.set_aspect(1.5);
+        let new_view: [[f32; 4]; 4] = camera.view.into();
+        assert_eq!(bytemuck::bytes_of(&view), bytemuck::bytes_of(&new_view));
+        assert_ne!(camera.build_projection_matrix(), projection);
+        // The uniform is rewritten on the next frame
+        assert!(camera.dirty);
+    }
+
+    #[test]
+    fn dedup_collapses_identical_instances() {
+        let instance = |x: f32| Instance {
+            position: cgmath::Vector3::new(x, 0.0, 0.0),
+            rotation: cgmath::Quaternion::one(),
+            scale: 1.0,
+            color: WHITE,
+        };
+        let deduped = dedup_instances(vec![instance(1.0), instance(2.0), instance(1.0 + 1e-7)]);
+        assert_eq!(deduped, vec![instance(1.0), instance(2.0)]);
+        // q and -q are the same rotation
+        let flipped = Instance { rotation: -cgmath::Quaternion::one(), ..instance(1.0) };
+        assert_eq!(dedup_instances(vec![instance(1.0), flipped]).len(), 1);
+    }
+
+    #[test]
+    fn msaa_4_multisamples_the_pipeline() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        if !state.gpu.format_features.sample_count_supported(4) {
+            eprintln!("4x MSAA isn't supported, skipping the test");
+            return;
+        }
+        assert!(state.gpu.msaa_target.is_none());
+        state.set_anti_aliasing(AntiAliasing::Msaa(4));
+        assert_eq!(state.anti_aliasing, AntiAliasing::Msaa(4));
+        assert_eq!(state.gpu.pipeline_config.sample_count, 4);
+        assert!(state.gpu.msaa_target.is_some());
+        // wgpu panics if the pipeline's sample count doesn't match the targets
+        let image = state.render_to_image(64, 32);
+        assert!(drawn_pixels(&image, 0..64) > 0);
+        state.set_anti_aliasing(AntiAliasing::None);
+        assert_eq!(state.gpu.pipeline_config.sample_count, 1);
+        assert!(state.gpu.msaa_target.is_none());
+    }
+
+    #[test]
+    fn applying_a_scene_sets_text_camera_and_effects() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        let scenes = demo_scenes("scene");
+        let marquee = scenes.iter().find(|s| s.name == "marquee").unwrap();
+        state.apply_scene(marquee);
+        assert_eq!(state.text, "scene");
+        assert_eq!(state.gpu.models[letter_index('s').unwrap()].instances.len(), 1);
+        assert!(state.gpu.models[letter_index('h').unwrap()].instances.is_empty());
+        assert_eq!(state.camera.eye, cgmath::Point3::new(0.0, -2.0, 9.0));
+        assert_eq!(state.camera.projection, Projection::Perspective { fovy: 35.0 });
+        assert_eq!(state.marquee_speed, 3.0);
+
+        let debug = scenes.iter().find(|s| s.name == "debug").unwrap();
+        state.apply_scene(debug);
+        assert_eq!(state.marquee_speed, 0.0);
+        assert!(state.debug_colors && state.show_bounds);
+        assert!(state.gpu.bounds_model.is_some());
+        assert_eq!(state.config_snapshot(), debug.config);
+    }
+
+    #[test]
+    fn multibyte_char_takes_one_letters_width() {
+        let layout = LayoutConfig { alignment: Alignment::Stretch, ..Default::default() };
+        let accented = get_letter_instances("h\u{e9}llo", &layout, &GlyphMap::default());
+        let ascii = get_letter_instances("hallo", &layout, &GlyphMap::default());
+        for c in ['h', 'l', 'o'] {
+            let i = letter_index(c).unwrap();
+            assert_eq!(accented[i].len(), ascii[i].len());
+            for (a, b) in accented[i].iter().zip(&ascii[i]) {
+                assert_close(a.position.x, b.position.x);
+                assert_close(a.scale, b.scale);
+            }
+        }
+    }
+
+    #[test]
+    fn show_bounds_draws_a_box_per_letter() {
+        let Some(mut state) = test_state("hello wo", 64, 32) else { return };
+        assert!(state.gpu.bounds_model.is_none());
+        state.set_show_bounds(true);
+        // Each box is 4 bars of 2 triangles, the space gets none
+        let bounds_model = state.gpu.bounds_model.as_ref().unwrap();
+        assert_eq!(bounds_model.vertex_data.num_indices, 7 * 4 * 6);
+        state.set_show_bounds(false);
+        assert!(state.gpu.bounds_model.is_none());
+    }
+
+    #[test]
+    fn pipeline_uses_the_configured_depth_bias() {
+        let depth_bias = wgpu::DepthBiasState { constant: -4, slope_scale: -1.0, clamp: 0.0 };
+        let config = PipelineConfig { depth_bias, ..Default::default() };
+        assert_eq!(depth_stencil_state(&config, false).unwrap().bias.constant, -4);
+        assert_eq!(depth_stencil_state(&config, true).unwrap().bias, depth_bias);
+        // Without a depth attachment there's nothing to bias
+        assert!(depth_stencil_state(&PipelineConfig { depth_format: None, ..config }, false).is_none());
+
+        let Some((device, _queue)) = test_device() else { return };
+        let (layout, shader) = test_pipeline_layout(&device);
+        // wgpu panics on a validation error
+        create_render_pipeline(&device, &layout, &shader, wgpu::TextureFormat::Rgba8UnormSrgb, &config, false);
+    }
+
+    #[test]
+    fn config_snapshot_round_trips() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        let config = StateConfig {
+            projection: Projection::Perspective { fovy: 30.0 },
+            displacement_focus: [0.25, -0.5],
+            displacement_strength: 0.3,
+            clear_color: [0.1, 0.2, 0.3, 1.0],
+            drop_shadow: Some(2.0),
+            outline: Some(([0.0, 0.0, 1.0, 0.5], 0.1)),
+            ..demo_scenes("round trip")[1].config.clone()
+        };
+        // Through json, the way a page would keep it in localStorage
+        let json = serde_json::to_string(&config).unwrap();
+        state.apply_config(&serde_json::from_str(&json).unwrap());
+        let snapshot = state.config_snapshot();
+        assert_eq!(snapshot, config);
+        assert_eq!(snapshot.projection, Projection::Perspective { fovy: 30.0 });
+        assert_eq!((snapshot.displacement_focus, snapshot.displacement_strength), ([0.25, -0.5], 0.3));
+
+        // Turning the effects back off round trips too
+        state.apply_config(&StateConfig::new("hello"));
+        assert_eq!(state.config_snapshot(), StateConfig::new("hello"));
+        assert!(state.gpu.shadow.is_none() && state.gpu.outline_pipeline.is_none());
+    }
+
+    #[test]
+    fn modifier_held_keys_are_shortcuts_not_text() {
+        use winit::keyboard::ModifiersState;
+        assert!(is_shortcut(ModifiersState::CONTROL));
+        assert!(is_shortcut(ModifiersState::ALT));
+        assert!(is_shortcut(ModifiersState::SUPER));
+        assert!(is_shortcut(ModifiersState::CONTROL | ModifiersState::SHIFT));
+        // Shift only changes the typed character
+        assert!(!is_shortcut(ModifiersState::SHIFT));
+        assert!(!is_shortcut(ModifiersState::empty()));
+    }
+
+    #[test]
+    fn headless_state_reports_the_adapter() {
+        let Some(state) = test_state("hello", 64, 32) else { return };
+        // Whatever the adapter is, the accessors describe it
+        let adapter = &state.gpu.adapter;
+        assert_eq!(state.supported_features(), adapter.features());
+        assert_eq!(state.backend(), adapter.get_info().backend);
+        assert_ne!(state.backend(), wgpu::Backend::Empty);
+        // The device only has features the adapter supports
+        assert!(state.supported_features().contains(state.gpu.device.features()));
+    }
+
+    #[test]
+    fn sorted_draws_go_back_to_front() {
+        let Some((device, _queue)) = test_device() else { return };
+        let alphabet = letters::create_alphabet_models(letters::DEFAULT_STROKE_WEIGHT);
+        let models = create_models(&device, "aba", &LayoutConfig::default(), &GlyphMap::default(), &alphabet);
+        // Seen from the left the rightmost letter is the farthest
+        let draws = back_to_front_draws(&models, cgmath::Point3::new(-20.0, 0.0, 7.0));
+        assert_eq!(draws, vec![(0, 1..2), (1, 0..1), (0, 0..1)]);
+        // From the right the order flips
+        let draws = back_to_front_draws(&models, cgmath::Point3::new(20.0, 0.0, 7.0));
+        assert_eq!(draws, vec![(0, 0..1), (1, 0..1), (0, 1..2)]);
+        // Adjacent instances of the same letter are drawn together
+        let models = create_models(&device, "aab", &LayoutConfig::default(), &GlyphMap::default(), &alphabet);
+        let draws = back_to_front_draws(&models, cgmath::Point3::new(20.0, 0.0, 7.0));
+        assert_eq!(draws, vec![(0, 0..2), (1, 0..1)]);
+    }
+
+    #[test]
+    fn glyph_map_routes_1_to_glyph_3() {
+        let glyph_map = GlyphMap::default().with('1', 3);
+        assert_eq!(glyph_map.glyph_index('1'), Some(3));
+        // Everything else keeps the default
+        assert_eq!(glyph_map.glyph_index('2'), letter_index('2'));
+        assert_eq!(glyph_map.glyph_index('a'), Some(0));
+        assert_eq!(glyph_map.glyph_index('!'), None);
+
+        let instances = get_letter_instances("11d", &LayoutConfig::default(), &glyph_map);
+        assert_eq!(instances[3].len(), 3);
+        assert!(instances[letter_index('1').unwrap()].is_empty());
+    }
+
+    #[test]
+    fn shared_states_share_glyph_buffers_but_not_text() {
+        let Some(mut first) = test_state("hello", 64, 32) else { return };
+        let mut second = first.share(None, winit::dpi::PhysicalSize::new(64, 32));
+        // The glyph geometry is only uploaded once, each state has its own instances
+        for (a, b) in first.gpu.models.iter().zip(&second.gpu.models) {
+            assert_eq!(a.vertex_data.vertex_buffer, b.vertex_data.vertex_buffer);
+            assert_eq!(a.vertex_data.index_buffer, b.vertex_data.index_buffer);
+            assert_ne!(a.instance_buffer, b.instance_buffer);
+        }
+        assert_eq!(first.gpu.render_pipeline, second.gpu.render_pipeline);
+        let before = first.render_to_image(64, 32);
+        assert_eq!(second.render_to_image(64, 32).values, before.values);
+
+        // Changing one's text leaves the other's alone
+        second.set_text("world");
+        assert_eq!(first.render_to_image(64, 32).values, before.values);
+        assert_ne!(second.render_to_image(64, 32).values, before.values);
+    }
+
+    #[test]
+    fn undersized_uniform_buffer_fails_binding() {
+        let Some((device, _queue)) = test_device() else { return };
+        let layout = create_camera_bind_group_layout(&device);
+        let buffer = |size: usize| device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: size as u64,
+            usage: wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        create_camera_bind_group(&device, &layout, &buffer(std::mem::size_of::<CameraUniform>()));
+        assert!(pollster::block_on(device.pop_error_scope()).is_none());
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        create_camera_bind_group(&device, &layout, &buffer(std::mem::size_of::<CameraUniform>() - 16));
+        assert!(pollster::block_on(device.pop_error_scope()).is_some());
+    }
+
+    #[test]
+    fn zero_blur_radius_matches_the_hard_shadow() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        let no_shadow = state.render_to_image(64, 32);
+        state.set_shadow_blur(0.0);
+        state.set_drop_shadow(true);
+        // A radius of 0.0 skips the blur passes
+        let hard = state.render_to_image(64, 32);
+        assert_ne!(hard.values, no_shadow.values);
+        // The blur passes run, but the neighboring texels get no weight
+        state.set_shadow_blur(1e-3);
+        assert_eq!(state.render_to_image(64, 32).values, hard.values);
+        state.set_shadow_blur(4.0);
+        assert_ne!(state.render_to_image(64, 32).values, hard.values);
+    }
+
+    #[test]
+    fn instanced_and_merged_render_the_same() {
+        let Some(mut state) = test_state("hello\nworld", 64, 32) else { return };
+        let instanced = state.render_to_image(64, 32);
+        assert!(drawn_pixels(&instanced, 0..64) > 0);
+        state.set_render_backend(RenderBackend::Merged);
+        assert!(state.gpu.merged.is_some());
+        assert_eq!(state.render_to_image(64, 32).values, instanced.values);
+        // Switching back drops the merged buffers
+        state.set_render_backend(RenderBackend::Instanced);
+        assert!(state.gpu.merged.is_none());
+        assert_eq!(state.render_to_image(64, 32).values, instanced.values);
+    }
+
+    #[test]
+    fn restricted_sample_mask_covers_fewer_samples() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        if !state.gpu.format_features.sample_count_supported(4) {
+            eprintln!("4x MSAA isn't supported, skipping the test");
+            return;
+        }
+        state.set_anti_aliasing(AntiAliasing::Msaa(4));
+        // The resolved alpha is the share of a pixel's samples the letters cover
+        let coverage = |image: &texture::RgbaTexture<[u8; 4]>| image.values.iter().map(|p| p[3] as u32).sum::<u32>();
+        let full = coverage(&state.render_to_image(64, 32));
+        state.set_sample_mask(0b0011);
+        assert_eq!(state.gpu.pipeline_config.sample_mask, 0b0011);
+        if state.backend() == wgpu::Backend::Gl {
+            eprintln!("The GL backend ignores the sample mask, skipping the readback");
+            return;
+        }
+        let half = coverage(&state.render_to_image(64, 32));
+        assert!(half > 0 && half < full, "{half} of {full}");
+    }
+
+    #[test]
+    fn line_numbers_are_right_aligned_in_the_gutter() {
+        let layout = LayoutConfig { line_numbers: true, ..Default::default() };
+        let text = ["ab"; 12].join("\n");
+        let instances = get_letter_instances(&text, &layout, &GlyphMap::default());
+        // Each digit's (digit, x, y), gathered into lines from the top down
+        let mut digits: Vec<(char, f32, f32)> = (0..10).flat_map(|d| {
+            let c = char::from_digit(d, 10).unwrap();
+            instances[letter_index(c).unwrap()].iter().map(move |i| (c, i.position.x, i.position.y))
+        }).collect();
+        digits.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.1.total_cmp(&b.1)));
+        let lines: Vec<Vec<(char, f32, f32)>> = digits.chunk_by(|a, b| a.2 == b.2).map(<[_]>::to_vec).collect();
+        assert_eq!(lines.len(), 12);
+        // The gutter is 2 digits wide, the last digit of every number is in its second column
+        let last_column = LEFT_BOUND + 1.5 * layout.advance;
+        for (i, line) in lines.iter().enumerate() {
+            let number: String = line.iter().map(|d| d.0).collect();
+            assert_eq!(number, (i + 1).to_string());
+            assert_close(line.last().unwrap().1, last_column);
+        }
+        // The text starts after the gutter and a space
+        let a = &instances[letter_index('a').unwrap()][0];
+        assert!(a.position.x > last_column + layout.advance);
+    }
+
+    #[test]
+    fn strength_decays_while_the_cursor_is_off_the_window() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        state.focus_points[0].strength = 0.4;
+        state.cursor_on_window = false;
+        let mut last = state.displacement_state().1;
+        for _ in 0..10 {
+            state.advance_animation(0.5);
+            let (focus, strength) = state.displacement_state();
+            assert!(strength < last);
+            assert_eq!(focus, state.focus_points[0].position);
+            last = strength;
+        }
+        // 5 seconds is over 6 half-lives
+        assert!(last < 0.4 / 64.0);
+    }
+
+    #[test]
+    fn focus_smoothing_halves_the_distance_every_half_life() {
+        let half_life = DEFAULT_FOCUS_HALF_LIFE;
+        assert!((focus_smoothing(half_life, half_life) - 0.5).abs() < 1e-6);
+        // Ten short steps cover the same distance as one long step
+        let mut remaining = 1.0;
+        for _ in 0..10 {
+            remaining *= 1.0 - focus_smoothing(0.1, half_life);
+        }
+        assert!((remaining - (1.0 - focus_smoothing(1.0, half_life))).abs() < 1e-6);
+        assert_eq!(focus_smoothing(0.1, 0.0), 1.0);
+    }
+
+    #[test]
+    fn texture_bind_group_builds_with_a_normal_map() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        let normal_map = texture::RgbaTexture::solid(2, 2, [128, 128, 255, 255], wgpu::TextureFormat::Rgba8Unorm);
+        state.gpu.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        state.set_normal_map(Some(&normal_map));
+        state.set_letter_texture(0, Some(&normal_map));
+        assert!(pollster::block_on(state.gpu.device.pop_error_scope()).is_none());
+        assert_eq!(state.light_uniform.normal_map, 1);
+        assert_eq!(state.gpu.letter_normal_texture.texture.size().width, 2);
+
+        state.set_normal_map(None);
+        assert_eq!(state.light_uniform.normal_map, 0);
+        assert_eq!(state.gpu.letter_normal_texture.texture.size().width, 1);
+    }
+
+    #[test]
+    fn placed_text_is_centered_on_the_world_point() {
+        let Some(mut state) = test_state("", 64, 32) else { return };
+        state.place_text_at(cgmath::Point3::new(3.0, -2.0, -1.5), "hoho");
+        let positions: Vec<_> = state.gpu.models.iter().flat_map(|m| &m.instances).map(|i| i.position).collect();
+        assert_eq!(positions.len(), 4);
+        let average = positions.iter().sum::<cgmath::Vector3<f32>>() / positions.len() as f32;
+        assert!((average.x - 3.0).abs() < 1e-4, "{average:?}");
+        assert!((average.y + 2.0).abs() < 1e-4, "{average:?}");
+        assert!((average.z + 1.5).abs() < 1e-4, "{average:?}");
+    }
+
+    #[test]
+    fn every_displacement_mode_writes_its_uniform_and_renders() {
+        let modes = [DisplacementMode::Push, DisplacementMode::Swirl, DisplacementMode::Wave, DisplacementMode::Ripple];
+        for (i, mode) in modes.into_iter().enumerate() {
+            assert_eq!(mode.uniform(false), [i as u32, 0, 0, 0]);
+            assert_eq!(mode.uniform(true), [i as u32, 1, 0, 0]);
+        }
+
+        // The pipeline compiled every branch of the shader, drawing with each mode must still validate
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        state.focus_points[0].strength = 0.4;
+        state.gpu.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        for mode in modes {
+            state.set_displacement_mode(mode);
+            assert_eq!(state.displacement_mode, mode);
+            assert!(drawn_pixels(&state.render_to_image(64, 32), 0..64) > 0);
+        }
+        assert!(pollster::block_on(state.gpu.device.pop_error_scope()).is_none());
+    }
+
+    #[test]
+    fn cocoa_draws_its_cs() {
+        let Some(mut state) = test_state("cocoa", 128, 32) else { return };
+        let cocoa = drawn_pixels(&state.render_to_image(128, 32), 0..128);
+        // Spaces keep the other letters where they were
+        state.set_text(" o oa");
+        let without_cs = drawn_pixels(&state.render_to_image(128, 32), 0..128);
+        assert!(cocoa > without_cs + without_cs / 4, "{cocoa} {without_cs}");
+    }
+
+    #[test]
+    fn spaces_digits_and_punctuation_dont_panic() {
+        assert_eq!(letter_index('A'), letter_index('a'));
+        assert_eq!(letter_index('7'), Some(33));
+        for c in [' ', '!', '.', ',', '?', '-', 'é'] {
+            assert_eq!(letter_index(c), None, "{c:?}");
+        }
+
+        let layout = LayoutConfig::default();
+        let count = |text: &str| get_letter_instances(text, &layout, &GlyphMap::default()).iter().map(Vec::len).sum::<usize>();
+        assert_eq!(count("Hello World!"), 10);
+        assert_eq!(count("42, 7."), 3);
+
+        // A space still takes up a letter's width
+        let xs = |text: &str| {
+            let mut xs: Vec<f32> = get_letter_instances(text, &layout, &GlyphMap::default())
+                .iter().flatten().map(|i| i.position.x).collect();
+            xs.sort_by(f32::total_cmp);
+            xs
+        };
+        let spaced = xs("a a");
+        let adjacent = xs("aa");
+        assert!((spaced[1] - spaced[0] - 2.0 * (adjacent[1] - adjacent[0])).abs() < 1e-4);
+    }
+
+    #[test]
+    fn picked_letters_go_through_the_glyph_map() {
+        let glyph_map = GlyphMap::default().with('!', 3).with('?', 30);
+        assert_eq!(glyph_map.glyph_char(0), Some('a'));
+        assert_eq!(glyph_map.glyph_char(30), Some('?'));
+        assert_eq!(glyph_map.glyph_char(35), Some('9'));
+        assert_eq!(glyph_map.glyph_char(3), Some('!'));
+        assert_eq!(glyph_map.glyph_char(31), Some('5'));
+        assert_eq!(GlyphMap::default().with('d', 0).glyph_char(3), None);
+
+        let Some(mut state) = test_state("", 64, 64) else { return };
+        state.set_glyph_map(GlyphMap::default().with('!', 3));
+        state.set_text("7!");
+        let center = |c: char| {
+            use cgmath::SquareMatrix;
+            let i = letter_index(c).unwrap();
+            let (instance, bounds) = (&state.gpu.models[i].instances[0], state.glyph_bounds[i].unwrap());
+            let world = instance.position + instance.scale * cgmath::Vector3::new(
+                (bounds.min[0] + bounds.max[0]) / 2.0,
+                (bounds.min[1] + bounds.max[1]) / 2.0,
+                0.0,
+            );
+            let clip = state.inverse_camera_mat.invert().unwrap() * world.extend(1.0);
+            [clip.x / clip.w, clip.y / clip.w]
+        };
+        assert_eq!(state.pick_letter(center('7')), Some(('7', 0)));
+        assert_eq!(state.pick_letter(center('d')), Some(('!', 0)));
+        assert_eq!(state.pick_letter([0.99, 0.99]), None);
+    }
+
+    #[test]
+    fn stretched_empty_line_is_one_advance_tall() {
+        let layout = LayoutConfig { alignment: Alignment::Stretch, ..Default::default() };
+        let instances = get_letter_instances("ab\n\nab", &layout, &GlyphMap::default());
+        let a = &instances[letter_index('a').unwrap()];
+        assert_eq!(a.len(), 2);
+        assert!(a.iter().all(|i| i.position.y.is_finite() && i.scale.is_finite()));
+        // The line heights of "ab" and the empty line between them
+        let line_height = a[0].scale / layout.scale_ratio;
+        assert_close(a[0].position.y - a[1].position.y, line_height + layout.advance);
+    }
+
+    #[test]
+    fn one_letter_line_is_centered_at_x_0() {
+        let layout = LayoutConfig::default();
+        assert_eq!(layout.alignment, Alignment::Center);
+        let instances = get_letter_instances("h", &layout, &GlyphMap::default());
+        let h = &instances[letter_index('h').unwrap()];
+        assert_eq!(h.len(), 1);
+        assert_close(h[0].position.x, 0.0);
+        // Short lines keep the fixed advance rather than stretching to the bounds
+        let long = get_letter_instances("hello world", &layout, &GlyphMap::default());
+        assert_close(h[0].scale, long[letter_index('h').unwrap()][0].scale);
+    }
+
+    #[test]
+    fn orthographic_x_doesnt_depend_on_depth() {
+        let mut camera = Camera::new_default(2.0);
+        // The corners of a quad centered in the view, at 2 depths
+        let ndc_x = |camera: &Camera, z: f32| {
+            let clip = camera.build_view_projection_matrix() * cgmath::Vector4::new(1.0, 1.0, z, 1.0);
+            clip.x / clip.w
+        };
+        assert!((ndc_x(&camera, 0.0) - ndc_x(&camera, -3.0)).abs() > 1e-3);
+
+        camera.set_projection(Projection::Orthographic { height: 4.0 });
+        assert_close(ndc_x(&camera, 0.0), ndc_x(&camera, -3.0));
+        // Half the view is 2.0 * aspect wide
+        assert_close(ndc_x(&camera, 0.0), 0.25);
+    }
+
+    #[test]
+    fn instance_raw_layout_matches_the_struct() {
+        let desc = InstanceRaw::desc();
+        assert_eq!(desc.array_stride, 80);
+        assert_eq!(desc.array_stride, std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress);
+        assert_eq!(desc.step_mode, wgpu::VertexStepMode::Instance);
+        // The 4 columns of the model matrix, then the color
+        for (i, attribute) in desc.attributes.iter().enumerate() {
+            assert_eq!(attribute.shader_location, 5 + i as u32);
+            assert_eq!(attribute.offset, 16 * i as wgpu::BufferAddress);
+            assert_eq!(attribute.format, wgpu::VertexFormat::Float32x4);
+        }
+        assert_eq!(desc.attributes.len(), 5);
+        assert_eq!(std::mem::offset_of!(InstanceRaw, color), 64);
+        // The vertex attributes come before them
+        assert!(letters::desc().attributes.iter().all(|a| a.shader_location < 5));
+    }
+
+    #[test]
+    fn strength_halves_every_half_life_for_any_step_count() {
+        let start = 0.4;
+        let expected = start * f32::exp2(-1.0 / STRENGTH_DECAY_HALF_LIFE);
+        for steps in [1, 7, 60, 144] {
+            let dt = 1.0 / steps as f32;
+            let strength = (0..steps).fold(start, |s, _| step_displacement_strength(s, dt, false, 1.0));
+            assert!((strength - expected).abs() < 1e-5, "{steps} steps: {strength} not {expected}");
+        }
+        let half = step_displacement_strength(start, STRENGTH_DECAY_HALF_LIFE, false, 1.0);
+        assert!((half - start / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn long_sentence_wraps_into_lines() {
+        let sentence = "the quick brown fox jumps over the lazy dog";
+        assert_eq!(wrap_lines(sentence, 10), ["the quick", "brown fox", "jumps over", "the lazy", "dog"]);
+        // Words longer than a line are broken
+        assert_eq!(wrap_lines("abcdefghijklm no", 5), ["abcde", "fghij", "klm", "no"]);
+        assert_eq!(wrap_lines("fits\n\nas is", 10), ["fits", "", "as is"]);
+
+        // The wrapped lines are stacked like any others
+        let layout = LayoutConfig { wrap: Some(10), ..Default::default() };
+        let mut ys: Vec<f32> = get_letter_instances(sentence, &layout, &GlyphMap::default())
+            .iter().flatten().map(|i| i.position.y).collect();
+        ys.sort_by(f32::total_cmp);
+        ys.dedup();
+        assert_eq!(ys.len(), 5);
+    }
+
+    #[test]
+    fn displacement_uniform_matches_the_shaders_focus_points() {
+        // WGSL lays out array<vec4<f32>, 8> with a 16-byte stride, the count after it, and rounds the
+        // struct up to its 16-byte alignment
+        assert_eq!(std::mem::offset_of!(DisplacementUniform, points), 0);
+        assert_eq!(std::mem::size_of::<[f32; 4]>(), 16);
+        assert_eq!(std::mem::offset_of!(DisplacementUniform, count), 16 * MAX_FOCUS_POINTS);
+        assert_eq!(std::mem::size_of::<DisplacementUniform>(), 144);
+        let array = format!("points: array<vec4<f32>, {MAX_FOCUS_POINTS}>,\n  count: u32,");
+        assert!(include_str!("shaders/shader.wgsl").contains(&array));
+        assert!(include_str!("shaders/displace.wgsl").contains(&array));
+
+        let points: Vec<[f32; 4]> = (0..10).map(|i| [i as f32, 0.0, 0.0, 1.0]).collect();
+        let uniform = DisplacementUniform::new(&points);
+        assert_eq!(uniform.count, MAX_FOCUS_POINTS as u32);
+        assert_eq!(uniform.points[7], points[7]);
+        let bytes = bytemuck::bytes_of(&uniform);
+        assert_eq!(&bytes[16 * 3..16 * 3 + 4], 3.0f32.to_ne_bytes());
+        assert_eq!(&bytes[128..132], (MAX_FOCUS_POINTS as u32).to_ne_bytes());
+    }
+
+    #[test]
+    fn instances_far_outside_the_frustum_are_culled() {
+        let Some(mut state) = test_state("a", 64, 64) else { return };
+        let instance = |x: f32, z: f32| Instance {
+            position: cgmath::Vector3::new(x, 0.0, z),
+            rotation: cgmath::Quaternion::one(),
+            scale: 1.0,
+            color: [1.0; 4],
+        };
+        let a = letter_index('a').unwrap();
+        state.gpu.models[a].instances = vec![instance(0.0, 0.0), instance(1e4, 0.0), instance(0.0, -1e4)];
+        // The default camera looks down -z at the origin from z = 7
+        let view_proj = Camera::new_default(1.0).build_view_projection_matrix();
+        let culled = cull_draws(vec![(a, 0..3)], &state.gpu.models, &state.glyph_bounds, view_proj, 0.0, 1.0);
+        assert_eq!(culled, [(a, 0..1)]);
+
+        // A margin as far as the shaders could move a letter keeps it
+        let culled = cull_draws(vec![(a, 0..3)], &state.gpu.models, &state.glyph_bounds, view_proj, 2e4, 1.0);
+        assert_eq!(culled, [(a, 0..3)]);
+    }
+
+    #[test]
+    fn scissor_is_clamped_to_the_surface_or_rejected() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        assert_eq!(state.set_scissor(Some((40, 8, 100, 100))), Ok(()));
+        assert_eq!(state.scissor, Some((40, 8, 24, 24)));
+
+        // Off the surface or without any area, the previous scissor is kept
+        for rect in [(64, 0, 10, 10), (0, 32, 10, 10), (0, 0, 0, 5), (0, 0, 5, 0)] {
+            let error = state.set_scissor(Some(rect)).unwrap_err();
+            assert_eq!(error, ScissorOutsideSurface { rect, surface: state.size });
+            assert_eq!(state.scissor, Some((40, 8, 24, 24)));
+        }
+        assert_eq!(state.set_scissor(None), Ok(()));
+        assert_eq!(state.scissor, None);
+    }
+
+    #[test]
+    fn anti_aliasing_always_matches_the_subsystems() {
+        let Some(mut state) = test_state("hello", 64, 32) else { return };
+        state.set_anti_aliasing(AntiAliasing::Supersample(2.0));
+        assert_eq!(state.scene_size(), winit::dpi::PhysicalSize::new(128, 64));
+        assert_eq!(state.config_snapshot().anti_aliasing, AntiAliasing::Supersample(2.0));
+
+        for scale in [-1.0, 0.0, f32::NAN, f32::INFINITY] {
+            state.set_anti_aliasing(AntiAliasing::Supersample(scale));
+            assert_eq!(state.anti_aliasing, AntiAliasing::None);
+            assert!(state.gpu.supersample.is_none());
+        }
+
+        state.set_anti_aliasing(AntiAliasing::Taa);
+        assert!(state.gpu.taa.is_some() && state.gpu.supersample.is_none());
+        state.set_anti_aliasing(AntiAliasing::Msaa(4));
+        assert!(state.gpu.taa.is_none());
+        assert!(state.gpu.msaa_target.is_some());
+        assert_eq!(state.gpu.pipeline_config.sample_count, 4);
+    }
+
+    #[test]
+    fn symmetric_block_is_centered_on_the_origin() {
+        // Justify starts each line at the left bound, so only these are symmetric
+        for alignment in [Alignment::Center, Alignment::Stretch] {
+            let layout = LayoutConfig { alignment, ..Default::default() };
+            let instances: Vec<Instance> = get_letter_instances("hello\nhello", &layout, &GlyphMap::default())
+                .into_iter().flatten().collect();
+            assert_eq!(instances.len(), 10);
+            let mean_x = instances.iter().map(|i| i.position.x).sum::<f32>() / 10.0;
+            assert_close(mean_x, 0.0);
+            // Vertically the glyphs' extents are centered, the positions are the bottoms of the letters so their
+            // average sits half a letter below the origin
+            let top = instances.iter().map(|i| i.position.y + i.scale).fold(f32::MIN, f32::max);
+            let bottom = instances.iter().map(|i| i.position.y).fold(f32::MAX, f32::min);
+            assert_close(top + bottom, 0.0);
+            let mean_y = instances.iter().map(|i| i.position.y).sum::<f32>() / 10.0;
+            assert_close(mean_y, -0.5 * instances[0].scale);
+        }
+    }
+}
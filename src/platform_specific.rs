@@ -1,7 +1,11 @@
+// The desktop window's size and title, overridden with WASM_WGPU_SIZE=WxH and WASM_WGPU_TITLE
+#[cfg(not(target_arch = "wasm32"))]
 pub const SIZE: winit::dpi::PhysicalSize::<u32> = winit::dpi::PhysicalSize::<u32> {
     width: 1280,
     height: 320,
 };
+#[cfg(not(target_arch = "wasm32"))]
+pub const TITLE: &str = "Test";
 
 #[allow(dead_code)]
 pub fn print(string: &str) {
@@ -30,7 +34,17 @@ pub fn instance_descriptor() -> wgpu::InstanceDescriptor {
     }
 }
 
-pub fn device_descriptor<'a>() -> wgpu::DeviceDescriptor<'a> {
+// The optional features desktop asks for when the adapter has them, device creation fails on a feature
+// the adapter doesn't have. POLYGON_MODE_LINE is for the wireframe, see State::set_wireframe. INDIRECT_FEATURES
+// draw the merged letters in one call. TIMESTAMP_QUERY times the main pass on the gpu
+#[cfg(not(target_arch = "wasm32"))]
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::POLYGON_MODE_LINE
+    .union(crate::INDIRECT_FEATURES)
+    .union(wgpu::Features::TIMESTAMP_QUERY);
+
+// adapter_features is what the adapter supports, optional features are only requested when it has them
+#[allow(unused_variables)]
+pub fn device_descriptor<'a>(adapter_features: wgpu::Features) -> wgpu::DeviceDescriptor<'a> {
     #[cfg(target_arch = "wasm32")]
     {
         wgpu::DeviceDescriptor {
@@ -41,6 +55,7 @@ pub fn device_descriptor<'a>() -> wgpu::DeviceDescriptor<'a> {
     #[cfg(not(target_arch = "wasm32"))]
     {
         wgpu::DeviceDescriptor {
+            required_features: adapter_features & OPTIONAL_FEATURES,
             ..Default::default()
         }
     }
@@ -68,6 +83,128 @@ pub fn window_attributes() -> winit::window::WindowAttributes {
     }
     #[cfg(not(target_arch = "wasm32"))]
     {
-        winit::window::WindowAttributes::default().with_title("Test").with_inner_size(SIZE)
+        let size = match std::env::var("WASM_WGPU_SIZE") {
+            Ok(value) => parse_size(&value).unwrap_or_else(|| {
+                print(&format!("WASM_WGPU_SIZE should be WIDTHxHEIGHT like 1280x320, not {value:?}. Using the default size"));
+                SIZE
+            }),
+            Err(_) => SIZE,
+        };
+        let title = std::env::var("WASM_WGPU_TITLE").unwrap_or_else(|_| TITLE.to_string());
+        winit::window::WindowAttributes::default().with_title(title).with_inner_size(size)
+    }
+}
+
+// Parses "1280x320" into a size. None unless both dimensions are nonzero
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_size(value: &str) -> Option<winit::dpi::PhysicalSize<u32>> {
+    let (width, height) = value.trim().split_once(['x', 'X'])?;
+    let (width, height) = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (width > 0 && height > 0).then(|| winit::dpi::PhysicalSize::new(width, height))
+}
+
+// Shows the frame rate, in the page's fps_element on wasm (if the page has one) and the console on desktop.
+// gpu_time is the milliseconds the gpu took for the main pass, when it can be measured
+pub fn show_frame_rate(frame_time: f32, gpu_time: Option<f32>) {
+    let mut text = format!("{:.0} fps ({:.1} ms)", 1.0 / frame_time.max(f32::EPSILON), frame_time * 1000.0);
+    if let Some(gpu_time) = gpu_time {
+        text += &format!(", gpu {gpu_time:.2} ms");
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wgpu::web_sys::wasm_bindgen::JsCast;
+        if let Some(element) = wgpu::web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("fps_element"))
+            .and_then(|e| e.dyn_into::<wgpu::web_sys::HtmlElement>().ok())
+        {
+            element.set_inner_text(&text);
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        print(&text);
+    }
+}
+
+// The localStorage key on wasm, and the directory in the config directory on desktop, the settings are saved under
+const SETTINGS_NAME: &str = "wasm-wgpu";
+
+// Saves the settings as JSON, in localStorage on wasm and in a file in the config directory on desktop.
+// Failures are only printed, the settings just won't be restored next time
+pub fn save_settings<T: serde::Serialize>(settings: &T) {
+    let json = match serde_json::to_string(settings) {
+        Ok(json) => json,
+        Err(e) => {
+            print(&format!("Couldn't serialize the settings: {e}"));
+            return;
+        }
+    };
+    #[cfg(target_arch = "wasm32")]
+    {
+        if let Some(Err(e)) = local_storage().map(|storage| storage.set_item(SETTINGS_NAME, &json)) {
+            print(&format!("Couldn't save the settings to localStorage: {e:?}"));
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let Some(path) = settings_path() else { return };
+        let result = std::fs::create_dir_all(path.parent().unwrap()).and_then(|()| std::fs::write(&path, json));
+        if let Err(e) = result {
+            print(&format!("Couldn't save the settings to {}: {e}", path.display()));
+        }
+    }
+}
+
+// The settings saved by save_settings. None when nothing was saved or it can't be read, e.g. after the
+// settings changed shape
+pub fn load_settings<T: serde::de::DeserializeOwned>() -> Option<T> {
+    #[cfg(target_arch = "wasm32")]
+    let json = local_storage()?.get_item(SETTINGS_NAME).ok()??;
+    #[cfg(not(target_arch = "wasm32"))]
+    let json = std::fs::read_to_string(settings_path()?).ok()?;
+    match serde_json::from_str(&json) {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+            print(&format!("Ignoring the saved settings: {e}"));
+            None
+        }
+    }
+}
+
+// None when the page can't use localStorage, e.g. when storage is disabled
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<wgpu::web_sys::Storage> {
+    wgpu::web_sys::window()?.local_storage().ok()?
+}
+
+// settings.json in $XDG_CONFIG_HOME, %APPDATA% or ~/.config
+#[cfg(not(target_arch = "wasm32"))]
+fn settings_path() -> Option<std::path::PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config")))?;
+    Some(config_dir.join(SETTINGS_NAME).join("settings.json"))
+}
+
+// Tells the user the demo can't run on their gpu or browser. The page shows a short message in its
+// error_element (if it has one), the underlying error goes to the console for debugging
+pub fn show_gpu_error(error: &dyn std::fmt::Display) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wgpu::web_sys::wasm_bindgen::JsCast;
+        print(&format!("Couldn't set up the gpu: {error}"));
+        if let Some(element) = wgpu::web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("error_element"))
+            .and_then(|e| e.dyn_into::<wgpu::web_sys::HtmlElement>().ok())
+        {
+            element.set_inner_text("Your browser doesn't support WebGL2, so the banner can't be drawn");
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        print(&format!("Couldn't find a gpu to draw with: {error}"));
     }
 }
@@ -0,0 +1,43 @@
+// Compute displacement. Each letter instance is pushed away from every focus point and toward the camera
+// as a whole, with the same falloff the vertex shader uses for the default radial push
+
+// Matches InstanceRaw
+struct Instance {
+  model: mat4x4<f32>,
+  color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<storage, read> instances: array<Instance>;
+@group(0) @binding(1)
+var<storage, read_write> displaced: array<Instance>;
+// Matches FocusPoints in shader.wgsl
+struct FocusPoints {
+  points: array<vec4<f32>, 8>,
+  count: u32,
+}
+
+@group(0) @binding(2)
+var<uniform> focus_points: FocusPoints;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let i = id.x;
+  if (i >= arrayLength(&instances)) {
+    return;
+  }
+  var instance = instances[i];
+  let center = instance.model[3].xyz;
+  var offset = vec3<f32>(0.0, 0.0, 0.0);
+  for (var p = 0u; p < min(focus_points.count, 8u); p++) {
+    let focus = focus_points.points[p];
+    let diff = center.xy - focus.xy;
+    let distance = length(diff);
+    let direction = select(vec2<f32>(0.0, 0.0), diff / distance, distance > 1e-6);
+    let xy_displacement = focus.w * 3.0 * (1.0 - pow(2.0, -distance)) * direction;
+    let z_displacement = 2.0 * focus.w / (1.0 + exp(1.5 * distance - 4.0));
+    offset += vec3<f32>(xy_displacement, z_displacement);
+  }
+  instance.model[3] = vec4<f32>(center + offset, 1.0);
+  displaced[i] = instance;
+}
@@ -0,0 +1,38 @@
+// Downsamples a supersampled frame into the output by averaging every source texel under each output pixel
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> params: vec4<f32>; // Only x is used, the number of taps along each axis. Padded to 16 bytes for web
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+// Draws a single triangle covering the whole screen, no vertex buffer needed
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+  let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.uv = uv;
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let taps = i32(params.x);
+  // The size of one output pixel in uv coordinates
+  let footprint = fwidth(in.uv);
+  var sum = vec4<f32>(0.0);
+  for (var x = 0; x < taps; x++) {
+    for (var y = 0; y < taps; y++) {
+      let offset = (vec2<f32>(f32(x), f32(y)) + 0.5) / f32(taps) - 0.5;
+      sum += textureSampleLevel(t_source, s_source, in.uv + offset * footprint, 0.0);
+    }
+  }
+  return sum / f32(taps * taps);
+}
@@ -7,11 +7,22 @@ var<uniform> screen_size: vec4<f32>; // Only vec2 is needed, but for web it must
 struct Light {
   position: vec3<f32>,
   color: vec3<f32>,
+  normal_map: u32, // Non-zero when t_letter_normal perturbs the lighting normal
 }
 
 @group(2) @binding(3)
 var<uniform> light: Light;
 
+struct Gradient {
+  top: vec4<f32>,
+  bottom: vec4<f32>,
+  enabled: u32, // Non-zero when the gradient replaces the letter texture color
+}
+
+// FRAGMENT visibility
+@group(2) @binding(4)
+var<uniform> gradient: Gradient;
+
 struct CameraUniform {
   view_pos: vec4<f32>,
   view_proj: mat4x4<f32>,
@@ -22,20 +33,45 @@ var<uniform> camera: CameraUniform;
 
 // Vertex shader
 
-// Displacement target is xyz and magnitude in the 4th position
+// Each point's xyz is its position and w its strength, only the first count are used. Matches DisplacementUniform
+struct FocusPoints {
+  points: array<vec4<f32>, 8>,
+  count: u32,
+}
+
 @group(2) @binding(0)
-var<uniform> displacement_target: vec4<f32>;
+var<uniform> focus_points: FocusPoints;
+
+// x is the scroll offset, and letters wrap within [y, y + z). A z of 0.0 disables the marquee
+@group(2) @binding(5)
+var<uniform> marquee: vec4<f32>;
+
+// x picks the displacement function: 0 radial push, 1 swirl, 2 wave, 3 ripple. Padded to 16 bytes for web
+// y is non-zero when the compute pass displaced the instances instead
+@group(2) @binding(6)
+var<uniform> displacement_mode: vec4<u32>;
+
+// The outline drawn behind the letters, only used by the outline pipeline. Matches OutlineUniform
+struct Outline {
+  color: vec4<f32>,
+  width: f32, // How far the outline reaches past each side of a letter, in units of the letter's size
+}
+
+@group(2) @binding(7)
+var<uniform> outline: Outline;
 
 struct InstanceInput {
   @location(5) model_matrix_0: vec4<f32>,
   @location(6) model_matrix_1: vec4<f32>,
   @location(7) model_matrix_2: vec4<f32>,
   @location(8) model_matrix_3: vec4<f32>,
+  @location(9) color: vec4<f32>,
 }
 
 struct VertexInput {
   @location(0) position: vec3<f32>,
   @location(1) tex_coords: vec2<f32>,
+  @location(2) normal: vec3<f32>,
 }
 
 struct VertexOutput {
@@ -44,14 +80,85 @@ struct VertexOutput {
   @location(1) world_normal: vec3<f32>,
   @location(2) world_position: vec3<f32>,
   @location(3) @interpolate(perspective) screen_pos: vec2<f32>, // web cannot @interpolate(linear)
+  @location(4) color: vec4<f32>, // The instance's tint
 };
 
+struct FocusEffect {
+  displacement: vec4<f32>,
+  // The gradient of the z displacement over xy, for the normal
+  slope: vec2<f32>,
+  // From 0 to 1, how strongly the radial push lifts the letters here. The idle wave fades out where it's high
+  z_strength: f32,
+}
+
+// The displacement around a single focus point, picked by displacement_mode.x
+fn focus_effect(position: vec2<f32>, focus: vec4<f32>, t: f32) -> FocusEffect {
+  let displacement_strength = focus.w;
+  let diff = position - focus.xy;
+  let xy_displacement = displacement_strength * 3.0 * (-1.0 * pow(2.0, -1.0 * length(diff)) + 1.0) * normalize(diff);
+  // Convenient variable for z_displacement and derivative_z_displacement
+  // pow_component = e^(1.5 * length(diff) - 4)
+  let exp_component = exp(1.5 * length(diff) - 4.0);
+  let z_displacement_strength = displacement_strength / (1.0 + exp_component);
+  let z_displacement = 2.0 * z_displacement_strength;
+  let distance = length(diff);
+
+  // Radial push by default
+  var displacement = vec4<f32>(xy_displacement, z_displacement, 0.0);
+  var slope = (-4.5 * displacement_strength) * exp_component / pow(2.0, exp_component + 1) * normalize(diff);
+  switch displacement_mode.x {
+    // Swirl: letters turn around the focus, the most near it
+    case 1u: {
+      let angle = 2.0 * displacement_strength * exp(-0.5 * distance);
+      let rotated = vec2<f32>(cos(angle) * diff.x - sin(angle) * diff.y, sin(angle) * diff.x + cos(angle) * diff.y);
+      displacement = vec4<f32>(rotated - diff, 0.0, 0.0);
+      slope = vec2<f32>(0.0, 0.0);
+    }
+    // Wave: a wave rolls along x through the focus
+    case 2u: {
+      let phase = 1.5 * diff.x - 3.0 * t;
+      displacement = vec4<f32>(0.0, 0.0, 2.0 * displacement_strength * sin(phase), 0.0);
+      slope = vec2<f32>(3.0 * displacement_strength * cos(phase), 0.0);
+    }
+    // Ripple: rings spread out from the focus and fade with distance
+    case 3u: {
+      let phase = 3.0 * distance - 6.0 * t;
+      let falloff = 1.5 * displacement_strength * exp(-0.3 * distance);
+      displacement = vec4<f32>(0.0, 0.0, falloff * sin(phase), 0.0);
+      slope = falloff * (3.0 * cos(phase) - 0.3 * sin(phase)) * normalize(diff);
+    }
+    default: {}
+  }
+  return FocusEffect(displacement, slope, z_displacement_strength);
+}
+
 @vertex 
 fn vs_main(
   model: VertexInput,
   instance: InstanceInput,
   @builtin(vertex_index) in_vertex_index: u32,
 ) -> VertexOutput {
+  return transform_vertex(model, instance);
+}
+
+// The glyphs span x = [-0.5, 0.5] and y = [0, 1] around this center. Matches GLYPH_CENTER in lib.rs
+const GLYPH_CENTER = vec2<f32>(0.0, 0.5);
+
+// The letter scaled up around its center in its own plane, drawn in a solid color behind the letters.
+// Thin strokes and the insides of letters get little to no outline, it's the scaled letter's silhouette
+@vertex
+fn vs_outline(
+  model: VertexInput,
+  instance: InstanceInput,
+) -> VertexOutput {
+  var scaled = model;
+  let xy = GLYPH_CENTER + (model.position.xy - GLYPH_CENTER) * (1.0 + 2.0 * outline.width);
+  scaled.position = vec3<f32>(xy, model.position.z);
+  return transform_vertex(scaled, instance);
+}
+
+// Moves a letter's vertex into clip space with the marquee, displacement and idle wave applied
+fn transform_vertex(model: VertexInput, instance: InstanceInput) -> VertexOutput {
   let t = time[0];
   let model_matrix = mat4x4<f32>(
     instance.model_matrix_0,
@@ -61,21 +168,30 @@ fn vs_main(
   );
   var out: VertexOutput;
   out.tex_coords = model.tex_coords;
-  let initial_world_position = model_matrix * vec4<f32>(model.position, 1.0);
-
-  let displacement_strength = displacement_target.w;
-  let diff = initial_world_position.xy - displacement_target.xy;
-  let xy_displacement = displacement_strength * 3.0 * (-1.0 * pow(2.0, -1.0 * length(diff)) + 1.0) * normalize(diff);
-  // Convenient variable for z_displacement and derivative_z_displacement
-  // pow_component = e^(1.5 * length(diff) - 4)
-  let exp_component = exp(1.5 * length(diff) - 4.0);
-  let z_displacement_strength = displacement_strength / (1.0 + exp_component);
-  // z_displacement_strength is a function that only outputs from 0 to 1, so invert that by subtracting from 1.
-  let inverse_z_displacement_strength = 1.0 - z_displacement_strength;
-  let z_displacement = 2.0 * z_displacement_strength;
+  out.color = instance.color;
+  var initial_world_position = model_matrix * vec4<f32>(model.position, 1.0);
 
+  // Marquee scrolling moves the whole letter by its center, so letters wrap around in one piece
+  let instance_x = instance.model_matrix_3.x;
+  let wrapped = instance_x - marquee.x - marquee.y;
+  let scrolled_x = marquee.y + wrapped - marquee.z * floor(wrapped / max(marquee.z, 1e-6));
+  initial_world_position.x += select(0.0, scrolled_x - instance_x, marquee.z > 0.0);
 
-  let displacement = vec4<f32>(xy_displacement, z_displacement, 0.0);
+  // Each focus point's displacement is added up. The compute pass already moved the instances when
+  // displacement_mode.y is set, see displace.wgsl
+  var displacement = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+  var slope = vec2<f32>(0.0, 0.0);
+  var z_displacement_strength = 0.0;
+  if (displacement_mode.y == 0u) {
+    for (var i = 0u; i < min(focus_points.count, 8u); i++) {
+      let effect = focus_effect(initial_world_position.xy, focus_points.points[i], t);
+      displacement += effect.displacement;
+      slope += effect.slope;
+      z_displacement_strength += effect.z_strength;
+    }
+  }
+  // z_displacement_strength is a function that only outputs from 0 to 1, so invert that by subtracting from 1.
+  let inverse_z_displacement_strength = 1.0 - min(z_displacement_strength, 1.0);
 
   // Transform the world position with sin/cos and time
   // Do this at an inverse rate to the z_displacement
@@ -92,18 +208,22 @@ fn vs_main(
   out.clip_position = camera.view_proj * world_position;
   out.screen_pos = vec2<f32>(0.5, 0.5) * (out.clip_position.xy / out.clip_position.w + vec2<f32>(1.0, 1.0));
 
-  // Calculate the normal. For now with my letters, every normal starts pointing straight up.
-  let normal = vec3<f32>(0.0, 0.0, 1.0);
-  // The normal is going to be perpendicular to the derivative of the z_displacement
-  let derivative_z_displacement = (-4.5 * displacement_strength) * exp_component / pow(2.0, exp_component + 1);
+  // Calculate the normal. The model's normal is rotated with the instance, the flat letters' point straight up
+  let normal = normalize((model_matrix * vec4<f32>(model.normal, 0.0)).xyz);
+  // The normal is going to be perpendicular to the derivative of the z displacement
   let derivative_wave = -1 * wave_transform.z; // The derivative is just *-1
-  out.world_normal = normalize(normal - vec3<f32>(derivative_z_displacement * normalize(diff), 0.0) + derivative_wave * vec3<f32>(1.0, 1.0, 0.0));
+  out.world_normal = normalize(normal - vec3<f32>(slope, 0.0) + derivative_wave * vec3<f32>(1.0, 1.0, 0.0));
 
   return out;
 }
 
 
 // Fragment shader
+@fragment
+fn fs_outline(in: VertexOutput) -> @location(0) vec4<f32> {
+  return outline.color;
+}
+
 @group(0) @binding(0)
 var t_letter: texture_2d<f32>;
 @group(0) @binding(1)
@@ -115,12 +235,14 @@ var s_letter_normal: sampler;
 
 @fragment 
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-  let object_color: vec4<f32> = textureSample(t_letter, s_letter, in.tex_coords);
+  let texture_color: vec4<f32> = textureSample(t_letter, s_letter, in.tex_coords);
+  // tex_coords.y is the letter's local y, from 0 at the bottom to 1 at the top
+  let gradient_color = mix(gradient.bottom, gradient.top, clamp(in.tex_coords.y, 0.0, 1.0));
+  let object_color = select(texture_color, vec4<f32>(gradient_color.rgb, texture_color.a), gradient.enabled != 0u) * in.color;
 
   // Sample normal and transform from the range [0.0, 1.0] -> [-1.0, 1.0]
   let sampled_normal: vec3<f32> = normalize(textureSample(t_letter_normal, s_letter_normal, in.tex_coords).xyz * 2.0 - vec3<f32>(1.0, 1.0, 1.0));
-  //let normal = normalize(in.world_normal + sampled_normal); // This code is for using the normal texture
-  let normal = in.world_normal;
+  let normal = select(in.world_normal, normalize(in.world_normal + sampled_normal), light.normal_map != 0u);
 
   let ambient_strength = 0.01;
   let ambient_color = light.color * ambient_strength;
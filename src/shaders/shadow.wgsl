@@ -0,0 +1,51 @@
+// Drop shadow. The letters are drawn into an offscreen texture, blurred with a separable gaussian and
+// then composited under the letters, offset and tinted
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+// Blur passes: xy is the direction of the blur in texels, z is the radius in texels.
+// Composite pass: xy is the offset of the shadow in texels, z is the opacity
+@group(0) @binding(2)
+var<uniform> params: vec4<f32>;
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+// Draws a single triangle covering the whole screen, no vertex buffer needed
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+  let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.uv = uv;
+  return out;
+}
+
+// One direction of the gaussian blur. Only the alpha of the letters matters for the shadow
+@fragment
+fn fs_blur(in: VertexOutput) -> @location(0) vec4<f32> {
+  let texel = params.xy / vec2<f32>(textureDimensions(t_source));
+  let radius = i32(ceil(params.z));
+  // The radius covers about 2 standard deviations
+  let sigma = max(params.z * 0.5, 0.001);
+  var sum = 0.0;
+  var weight_sum = 0.0;
+  for (var i = -radius; i <= radius; i++) {
+    let weight = exp(-f32(i * i) / (2.0 * sigma * sigma));
+    sum += weight * textureSampleLevel(t_source, s_source, in.uv + f32(i) * texel, 0.0).a;
+    weight_sum += weight;
+  }
+  return vec4<f32>(0.0, 0.0, 0.0, sum / weight_sum);
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+  let offset = params.xy / vec2<f32>(textureDimensions(t_source));
+  // Positive y moves the shadow down, uv y points down the screen
+  let alpha = textureSampleLevel(t_source, s_source, in.uv - offset, 0.0).a;
+  return vec4<f32>(0.0, 0.0, 0.0, alpha * params.z);
+}
@@ -0,0 +1,53 @@
+// Temporal anti-aliasing resolve, blends the current (jittered) frame into the accumulated history
+
+@group(0) @binding(0)
+var t_current: texture_2d<f32>;
+@group(0) @binding(1)
+var t_history: texture_2d<f32>;
+@group(0) @binding(2)
+var s_screen: sampler;
+@group(0) @binding(3)
+var<uniform> params: vec4<f32>; // Only x is used, the weight of the current frame. Padded to 16 bytes for web
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+// Draws a single triangle covering the whole screen, no vertex buffer needed
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+  let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+  out.uv = uv;
+  return out;
+}
+
+struct FragmentOutput {
+  @location(0) color: vec4<f32>,
+  @location(1) history: vec4<f32>,
+};
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+  let current = textureSampleLevel(t_current, s_screen, in.uv, 0.0);
+  let history = textureSampleLevel(t_history, s_screen, in.uv, 0.0);
+
+  // Clamp the history to the neighborhood of the current pixel, otherwise the animated letters leave trails
+  let texel = 1.0 / vec2<f32>(textureDimensions(t_current));
+  var low = current;
+  var high = current;
+  for (var x = -1; x <= 1; x++) {
+    for (var y = -1; y <= 1; y++) {
+      let neighbor = textureSampleLevel(t_current, s_screen, in.uv + vec2<f32>(f32(x), f32(y)) * texel, 0.0);
+      low = min(low, neighbor);
+      high = max(high, neighbor);
+    }
+  }
+
+  var out: FragmentOutput;
+  out.color = mix(clamp(history, low, high), current, params.x);
+  out.history = out.color;
+  return out;
+}
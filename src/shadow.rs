@@ -0,0 +1,228 @@
+// shadow.rs
+//
+// Soft drop shadows. The letters are drawn into an offscreen texture, which is blurred horizontally
+// then vertically with a gaussian, and composited under the letters at the start of the main pass.
+
+use crate::texture;
+
+// The shadow's offset from the letters in pixels of the scene target, positive y is down
+const OFFSET: [f32; 2] = [6.0, 6.0];
+const OPACITY: f32 = 0.5;
+
+pub struct Shadow {
+    radius: f32,
+    // The letters are drawn into targets[0], the horizontal blur writes targets[1] and the vertical blur writes
+    // back into targets[0]
+    targets: [texture::GpuTexture; 2],
+    blur_bind_groups: [wgpu::BindGroup; 2],
+    composite_bind_group: wgpu::BindGroup,
+    // Horizontal blur, vertical blur, composite
+    params_buffers: [wgpu::Buffer; 3],
+    blur_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl Shadow {
+    // sample_count and depth_format must match the pass the shadow is composited into, and size the scene target.
+    // Recreate the shadow when any of them change
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+        sample_count: u32,
+        depth_format: Option<wgpu::TextureFormat>,
+        radius: f32,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(16), // vec4<f32>
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("shadow_bind_group_layout"),
+        });
+
+        let params = [
+            [1.0, 0.0, radius, 0.0],
+            [0.0, 1.0, radius, 0.0],
+            [OFFSET[0], OFFSET[1], OPACITY, 0.0],
+        ];
+        let params_buffers = params.map(|p| wgpu::util::DeviceExt::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("shadow_params_buffer"),
+            contents: bytemuck::cast_slice(&p),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        }));
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shadow_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shadow.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("shadow_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, entry_point, blend, count, depth_stencil| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some(entry_point),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil,
+            multisample: wgpu::MultisampleState {
+                count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+        // The shadow is behind everything, it neither tests nor writes the depth
+        let composite_depth = depth_format.map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        });
+        let blur_pipeline = create_pipeline("shadow_blur_pipeline", "fs_blur", None, 1, None);
+        let composite_pipeline = create_pipeline(
+            "shadow_composite_pipeline",
+            "fs_composite",
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+            sample_count,
+            composite_depth,
+        );
+
+        let (targets, blur_bind_groups, composite_bind_group) =
+            Self::create_targets(device, &bind_group_layout, &params_buffers, format, size);
+        Shadow {
+            radius,
+            targets,
+            blur_bind_groups,
+            composite_bind_group,
+            params_buffers,
+            blur_pipeline,
+            composite_pipeline,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        params_buffers: &[wgpu::Buffer; 3],
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> ([texture::GpuTexture; 2], [wgpu::BindGroup; 2], wgpu::BindGroup) {
+        let targets = [
+            texture::GpuTexture::render_target(device, format, size.width, size.height, "shadow_target_0"),
+            texture::GpuTexture::render_target(device, format, size.width, size.height, "shadow_target_1"),
+        ];
+        let bind_group = |source: &texture::GpuTexture, params: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&source.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+            label: Some("shadow_bind_group"),
+        });
+        let blur_bind_groups = [
+            bind_group(&targets[0], &params_buffers[0]),
+            bind_group(&targets[1], &params_buffers[1]),
+        ];
+        let composite_bind_group = bind_group(&targets[0], &params_buffers[2]);
+        (targets, blur_bind_groups, composite_bind_group)
+    }
+
+    // The blur radius in pixels of the scene target, 0.0 gives a hard shadow
+    pub fn set_radius(&mut self, queue: &wgpu::Queue, radius: f32) {
+        self.radius = radius;
+        queue.write_buffer(&self.params_buffers[0], 0, bytemuck::cast_slice(&[1.0, 0.0, radius, 0.0]));
+        queue.write_buffer(&self.params_buffers[1], 0, bytemuck::cast_slice(&[0.0, 1.0, radius, 0.0]));
+    }
+
+    // The texture the letters should be drawn into for the shadow
+    pub fn target(&self) -> &wgpu::TextureView {
+        &self.targets[0].view
+    }
+
+    // Blurs the letters drawn into target(). Skipped entirely for a hard shadow
+    pub fn blur(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.radius <= 0.0 {
+            return;
+        }
+        for (bind_group, output) in self.blur_bind_groups.iter().zip([&self.targets[1], &self.targets[0]]) {
+            let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("shadow_blur"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &output.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            renderpass.set_pipeline(&self.blur_pipeline);
+            renderpass.set_bind_group(0, bind_group, &[]);
+            renderpass.draw(0..3, 0..1);
+        }
+    }
+
+    // Draws the blurred shadow into a pass, before the letters are drawn over it.
+    // This changes the pass's pipeline and bind group 0
+    pub fn composite(&self, renderpass: &mut wgpu::RenderPass) {
+        renderpass.set_pipeline(&self.composite_pipeline);
+        renderpass.set_bind_group(0, &self.composite_bind_group, &[]);
+        renderpass.draw(0..3, 0..1);
+    }
+}
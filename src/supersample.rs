@@ -0,0 +1,197 @@
+// supersample.rs
+//
+// Supersampling. The scene is rendered into an offscreen texture at a multiple of the surface
+// resolution, then downsampled into the output by averaging the texels under each output pixel.
+
+use crate::texture;
+
+pub struct Supersample {
+    scale: f32,
+    target: texture::GpuTexture,
+    bind_group: wgpu::BindGroup,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+}
+
+impl Supersample {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, size: winit::dpi::PhysicalSize<u32>, scale: f32) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(16), // vec4<f32>
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("downsample_bind_group_layout"),
+        });
+
+        // Enough taps along each axis to touch every source texel under an output pixel
+        let taps = scale.ceil().max(1.0);
+        let params_buffer = wgpu::util::DeviceExt::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("downsample_params_buffer"),
+            contents: bytemuck::cast_slice(&[taps, 0.0, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("downsample_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/downsample.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("downsample_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("downsample_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let (target, bind_group) = Self::create_target(device, &bind_group_layout, &params_buffer, format, size, scale);
+        Supersample {
+            scale,
+            target,
+            bind_group,
+            bind_group_layout,
+            params_buffer,
+            pipeline,
+            format,
+        }
+    }
+
+    // The size of the offscreen texture for a given surface size
+    pub fn scaled_size(size: winit::dpi::PhysicalSize<u32>, scale: f32, max_dimension: u32) -> winit::dpi::PhysicalSize<u32> {
+        let scale_dimension = |d: u32| ((d as f32 * scale).round() as u32).clamp(1, max_dimension);
+        winit::dpi::PhysicalSize::new(scale_dimension(size.width), scale_dimension(size.height))
+    }
+
+    fn create_target(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+        scale: f32,
+    ) -> (texture::GpuTexture, wgpu::BindGroup) {
+        let scaled = Self::scaled_size(size, scale, device.limits().max_texture_dimension_2d);
+        let target = texture::GpuTexture::render_target(device, format, scaled.width, scaled.height, "supersample_target");
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&target.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&target.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("downsample_bind_group"),
+        });
+        (target, bind_group)
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) {
+        (self.target, self.bind_group) =
+            Self::create_target(device, &self.bind_group_layout, &self.params_buffer, self.format, size, self.scale);
+    }
+
+    // The texture the scene should be rendered into when supersampling
+    pub fn target(&self) -> &wgpu::TextureView {
+        &self.target.view
+    }
+
+    pub fn target_size(&self) -> wgpu::Extent3d {
+        self.target.texture.size()
+    }
+
+    // Downsamples the rendered scene into output
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("downsample"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        renderpass.set_pipeline(&self.pipeline);
+        renderpass.set_bind_group(0, &self.bind_group, &[]);
+        renderpass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_is_twice_the_size_at_scale_2() {
+        let size = winit::dpi::PhysicalSize::new(1280, 320);
+        assert_eq!(Supersample::scaled_size(size, 2.0, 8192), winit::dpi::PhysicalSize::new(2560, 640));
+        // Never smaller than a pixel or larger than the device allows
+        assert_eq!(Supersample::scaled_size(size, 0.0001, 8192), winit::dpi::PhysicalSize::new(1, 1));
+        assert_eq!(Supersample::scaled_size(size, 10.0, 8192), winit::dpi::PhysicalSize::new(8192, 3200));
+
+        let Some((device, _queue)) = crate::tests::test_device() else { return };
+        let supersample = Supersample::new(&device, wgpu::TextureFormat::Rgba8UnormSrgb, size, 2.0);
+        let target = supersample.target_size();
+        assert_eq!((target.width, target.height), (2560, 640));
+    }
+}
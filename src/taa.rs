@@ -0,0 +1,264 @@
+// taa.rs
+//
+// Temporal anti-aliasing. Each frame the projection is jittered by a sub-pixel offset, the scene is
+// rendered into an offscreen texture, and a resolve pass blends it into a history texture that
+// accumulates the jittered samples over time. The thin letter strokes benefit the most.
+
+use crate::texture;
+
+// Number of jitter offsets before the pattern repeats
+pub const JITTER_SAMPLES: usize = 8;
+// How much of the current frame is blended into the history each frame
+const CURRENT_FRAME_WEIGHT: f32 = 0.1;
+
+// Returns the sub-pixel jitter for a frame in pixels, within [-0.5, 0.5].
+// Cycles through the first JITTER_SAMPLES points of the Halton(2, 3) sequence
+pub fn jitter_offset(frame: usize) -> [f32; 2] {
+    // Start at index 1, index 0 of a Halton sequence is always (0, 0)
+    let index = frame % JITTER_SAMPLES + 1;
+    [halton(index, 2) - 0.5, halton(index, 3) - 0.5]
+}
+
+fn halton(mut index: usize, base: usize) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+pub struct Taa {
+    frame: usize,
+    // False until a frame has been accumulated, the first frame is used as-is
+    history_valid: bool,
+    // The history texture read this frame, the other one is written to
+    history_index: usize,
+    current: texture::GpuTexture,
+    history: [texture::GpuTexture; 2],
+    // bind_groups[i] reads from history[i]
+    bind_groups: [wgpu::BindGroup; 2],
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+}
+
+impl Taa {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, size: winit::dpi::PhysicalSize<u32>) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(16), // vec4<f32>
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("taa_bind_group_layout"),
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("taa_params_buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("taa_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/taa.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("taa_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let target = Some(wgpu::ColorTargetState {
+            format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("taa_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                // The output and the next frame's history
+                targets: &[target.clone(), target],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let (current, history, bind_groups) = Self::create_targets(device, &bind_group_layout, &params_buffer, format, size);
+        Taa {
+            frame: 0,
+            history_valid: false,
+            history_index: 0,
+            current,
+            history,
+            bind_groups,
+            bind_group_layout,
+            params_buffer,
+            pipeline,
+            format,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> (texture::GpuTexture, [texture::GpuTexture; 2], [wgpu::BindGroup; 2]) {
+        let current = texture::GpuTexture::render_target(device, format, size.width, size.height, "taa_current");
+        let history = [
+            texture::GpuTexture::render_target(device, format, size.width, size.height, "taa_history_0"),
+            texture::GpuTexture::render_target(device, format, size.width, size.height, "taa_history_1"),
+        ];
+        let bind_groups = std::array::from_fn(|i| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&current.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&history[i].view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&current.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("taa_bind_group"),
+        }));
+        (current, history, bind_groups)
+    }
+
+    // Recreates the offscreen textures at the new size, which throws away the accumulated history
+    pub fn resize(&mut self, device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) {
+        (self.current, self.history, self.bind_groups) =
+            Self::create_targets(device, &self.bind_group_layout, &self.params_buffer, self.format, size);
+        self.history_valid = false;
+    }
+
+    // Returns the clip-space jitter for the next frame, to be applied on top of the view projection matrix
+    pub fn next_jitter(&mut self, size: winit::dpi::PhysicalSize<u32>) -> cgmath::Matrix4<f32> {
+        let offset = jitter_offset(self.frame);
+        self.frame = (self.frame + 1) % JITTER_SAMPLES;
+        // Pixels -> NDC. Translating in clip space by offset * w moves every vertex by offset after the perspective divide
+        cgmath::Matrix4::from_translation(cgmath::Vector3::new(
+            2.0 * offset[0] / size.width.max(1) as f32,
+            2.0 * offset[1] / size.height.max(1) as f32,
+            0.0,
+        ))
+    }
+
+    // The texture the scene should be rendered into when TAA is enabled
+    pub fn target(&self) -> &wgpu::TextureView {
+        &self.current.view
+    }
+
+    // Blends the rendered scene into the history and writes the result to output
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue, output: &wgpu::TextureView) {
+        let weight = if self.history_valid { CURRENT_FRAME_WEIGHT } else { 1.0 };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[weight, 0.0, 0.0, 0.0]));
+
+        let write_index = 1 - self.history_index;
+        let attachment = |view| Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: wgpu::StoreOp::Store,
+            },
+        });
+        let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("taa_resolve"),
+            color_attachments: &[attachment(output), attachment(&self.history[write_index].view)],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        renderpass.set_pipeline(&self.pipeline);
+        renderpass.set_bind_group(0, &self.bind_groups[self.history_index], &[]);
+        renderpass.draw(0..3, 0..1);
+
+        self.history_index = write_index;
+        self.history_valid = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_follows_halton_and_cycles() {
+        let expected = [
+            [0.0, 1.0 / 3.0 - 0.5],
+            [-0.25, 2.0 / 3.0 - 0.5],
+            [0.25, 1.0 / 9.0 - 0.5],
+            [-0.375, 4.0 / 9.0 - 0.5],
+        ];
+        for (frame, expected) in expected.iter().enumerate() {
+            let offset = jitter_offset(frame);
+            assert!((offset[0] - expected[0]).abs() < 1e-6 && (offset[1] - expected[1]).abs() < 1e-6, "{frame}: {offset:?}");
+        }
+        for frame in 0..JITTER_SAMPLES {
+            assert_eq!(jitter_offset(frame), jitter_offset(frame + JITTER_SAMPLES));
+            assert!(jitter_offset(frame).iter().all(|o| (-0.5..=0.5).contains(o)));
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub use glyph_core::pixel::{Downsample, Premultiply};
 
 #[derive(Clone)]
 pub struct RgbaTexture<T>
@@ -10,77 +11,396 @@ where T : bytemuck::Pod + bytemuck::Zeroable {
 
 impl<T> RgbaTexture<T>
 where T: bytemuck::Pod + bytemuck::Zeroable {
+    // A width * height texture where every pixel is color
+    pub fn solid(width: u32, height: u32, color: T, format: wgpu::TextureFormat) -> Self {
+        RgbaTexture {
+            values: vec![color; width as usize * height as usize],
+            format,
+            height,
+            width,
+        }
+    }
+
+    // An x past the width would silently wrap onto the next row, so debug builds check both coordinates
     pub fn set_pixel(&mut self, x: u32, y: u32, pixel: T) {
+        self.debug_assert_in_bounds(x, y);
         let idx = (x + y * self.width) as usize;
         self.values[idx] = pixel;
     }
 
     pub fn get_pixel(&self, x: u32, y: u32) -> T {
+        self.debug_assert_in_bounds(x, y);
         let idx = (x + y * self.width) as usize;
         self.values[idx]
     }
+
+    pub fn try_set_pixel(&mut self, x: u32, y: u32, pixel: T) -> Result<(), PixelOutOfBounds> {
+        self.check_bounds(x, y)?;
+        self.set_pixel(x, y, pixel);
+        Ok(())
+    }
+
+    pub fn try_get_pixel(&self, x: u32, y: u32) -> Result<T, PixelOutOfBounds> {
+        self.check_bounds(x, y)?;
+        Ok(self.get_pixel(x, y))
+    }
+
+    fn check_bounds(&self, x: u32, y: u32) -> Result<(), PixelOutOfBounds> {
+        if x < self.width && y < self.height {
+            Ok(())
+        } else {
+            Err(PixelOutOfBounds { x, y, width: self.width, height: self.height })
+        }
+    }
+
+    fn debug_assert_in_bounds(&self, x: u32, y: u32) {
+        if cfg!(debug_assertions) {
+            if let Err(e) = self.check_bounds(x, y) {
+                panic!("{}", e);
+            }
+        }
+    }
+}
+
+// A pixel coordinate outside of a RgbaTexture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelOutOfBounds {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::fmt::Display for PixelOutOfBounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Pixel ({}, {}) is outside of a {}x{} texture", self.x, self.y, self.width, self.height)
+    }
 }
 
+impl std::error::Error for PixelOutOfBounds {}
+
+impl RgbaTexture<[u8; 4]> {
+    // Decodes a PNG or JPEG, e.g. embedded with include_bytes! so it works on wasm too. Images are
+    // assumed to be sRGB color, use a Rgba8Unorm format instead for data like normal maps
+    pub fn from_image_bytes(bytes: &[u8]) -> Result<Self, image::ImageError> {
+        let image = image::load_from_memory(bytes)?.into_rgba8();
+        let (width, height) = image.dimensions();
+        Ok(RgbaTexture {
+            values: bytemuck::cast_slice(image.as_raw()).to_vec(),
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            height,
+            width,
+        })
+    }
+
+    // Writes the texture to a PNG file. The values are written as they are, whatever the format
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> Result<(), image::ImageError> {
+        image::save_buffer_with_format(
+            path,
+            bytemuck::cast_slice(&self.values),
+            self.width,
+            self.height,
+            image::ExtendedColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+    }
+
+    // Resamples to new_width * new_height with bilinear filtering, e.g. to fit an image to a power of two
+    // before uploading. Pixel centers line up, so the edges are clamped rather than wrapped
+    pub fn scaled(&self, new_width: u32, new_height: u32) -> Self {
+        // There's nothing to sample in an empty texture, it scales to an empty one
+        if self.width == 0 || self.height == 0 {
+            return RgbaTexture {
+                values: vec![],
+                format: self.format,
+                height: 0,
+                width: 0,
+            };
+        }
+        // Where a destination pixel's center lands in the source, split into the pixel to the left and the
+        // weight of the one to its right
+        let source = |i: u32, size: u32, new_size: u32| {
+            let p = ((i as f32 + 0.5) * size as f32 / new_size as f32 - 0.5).clamp(0.0, (size - 1) as f32);
+            let left = p.floor() as u32;
+            (left, (left + 1).min(size - 1), p - left as f32)
+        };
+        let lerp = |a: [f32; 4], b: [f32; 4], t: f32| std::array::from_fn(|c| a[c] + (b[c] - a[c]) * t);
+        let pixel = |x: u32, y: u32| self.get_pixel(x, y).map(|c| c as f32);
+
+        let mut values = Vec::with_capacity((new_width * new_height) as usize);
+        for y in 0..new_height {
+            let (top, bottom, ty) = source(y, self.height, new_height);
+            for x in 0..new_width {
+                let (left, right, tx) = source(x, self.width, new_width);
+                let color: [f32; 4] = lerp(
+                    lerp(pixel(left, top), pixel(right, top), tx),
+                    lerp(pixel(left, bottom), pixel(right, bottom), tx),
+                    ty,
+                );
+                values.push(color.map(|c| c.round() as u8));
+            }
+        }
+        RgbaTexture {
+            values,
+            format: self.format,
+            height: new_height,
+            width: new_width,
+        }
+    }
+}
+
+// Copies between buffers and textures require every row to start at a multiple of
+// wgpu::COPY_BYTES_PER_ROW_ALIGNMENT (256) bytes. Returns the row stride rounded up to that alignment
+pub fn padded_bytes_per_row(bytes_per_row: u32) -> u32 {
+    bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+// Spreads tightly packed rows out to padded_bytes_per_row, borrowing when no padding is needed
+pub fn pad_rows(bytes: &[u8], bytes_per_row: u32, padded_bytes_per_row: u32) -> std::borrow::Cow<'_, [u8]> {
+    if bytes_per_row == padded_bytes_per_row {
+        return std::borrow::Cow::Borrowed(bytes);
+    }
+    let mut padded = vec![];
+    for row in bytes.chunks(bytes_per_row as usize) {
+        padded.extend_from_slice(row);
+        padded.resize(padded.len() + (padded_bytes_per_row - bytes_per_row) as usize, 0);
+    }
+    std::borrow::Cow::Owned(padded)
+}
+
+// The inverse of pad_rows, for data read back from a texture
+pub fn unpad_rows(bytes: &[u8], bytes_per_row: u32, padded_bytes_per_row: u32) -> Vec<u8> {
+    bytes.chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..bytes_per_row as usize])
+        .copied()
+        .collect()
+}
+
+// Finishes encoder with a copy of the texture's first mip level into a buffer, and returns its bytes
+// tightly packed. The texture needs COPY_SRC usage. Blocks until the gpu is done, which wasm can't do
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, mut encoder: wgpu::CommandEncoder, texture: &wgpu::Texture) -> Vec<u8> {
+    let size = wgpu::Extent3d { depth_or_array_layers: 1, ..texture.size() };
+    let bytes_per_row = texture.format().block_copy_size(None).expect("Can't read back a depth texture") * size.width;
+    let padded_bytes_per_row = padded_bytes_per_row(bytes_per_row);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("read_texture_buffer"),
+        size: (padded_bytes_per_row * size.height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(size.height),
+            },
+        },
+        size,
+    );
+    queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.expect("Failed to map the texture's buffer"));
+    device.poll(wgpu::Maintain::Wait);
+    let bytes = unpad_rows(&slice.get_mapped_range(), bytes_per_row, padded_bytes_per_row);
+    buffer.unmap();
+    bytes
+}
+
+// The number of mip levels down to 1x1 for a texture of this size
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+// Halves a width * height image with a 2x2 box filter. Sizes of 1 stay 1, and the last row or column
+// of an odd size is dropped
+fn downsample<T: Downsample>(values: &[T], width: u32, height: u32) -> Vec<T> {
+    let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+    let pixel = |x: u32, y: u32| values[(x.min(width - 1) + y.min(height - 1) * width) as usize];
+    let mut half = Vec::with_capacity((half_width * half_height) as usize);
+    for y in 0..half_height {
+        for x in 0..half_width {
+            let (x, y) = (2 * x, 2 * y);
+            half.push(T::average([pixel(x, y), pixel(x + 1, y), pixel(x, y + 1), pixel(x + 1, y + 1)]));
+        }
+    }
+    half
+}
+
+// How a GpuTexture is sampled. The default suits the letter textures: linear magnification, mirrored
+// past the edges. Pixel art wants Nearest filters, and images that shouldn't repeat want ClampToEdge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerConfig {
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    // Only matters for textures with mipmaps
+    pub mipmap_filter: wgpu::FilterMode,
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub address_mode_w: wgpu::AddressMode,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        SamplerConfig {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::MirrorRepeat,
+            address_mode_v: wgpu::AddressMode::MirrorRepeat,
+            address_mode_w: wgpu::AddressMode::MirrorRepeat,
+        }
+    }
+}
 
+#[derive(Debug, Clone)]
 pub struct GpuTexture {
-    #[allow(dead_code)]
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler
 }
 
 impl GpuTexture {
-    pub fn from_rgbatexture<T: bytemuck::Pod + bytemuck::Zeroable>(
+    // premultiply_alpha should be set when uploading straight alpha images that will be alpha blended.
+    // mipmaps builds every mip level on the cpu with a box filter, so the texture doesn't alias when minified
+    pub fn from_rgbatexture<T: bytemuck::Pod + bytemuck::Zeroable + Premultiply + Downsample>(
         rgba: &RgbaTexture<T>,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         label: &str,
+        premultiply_alpha: bool,
+        mipmaps: bool,
+        sampler_config: SamplerConfig,
     ) -> Self {
         let texture_size = wgpu::Extent3d {
             width: rgba.width,
             height: rgba.height,
             depth_or_array_layers: 1,
         };
+        let mip_level_count = if mipmaps { mip_level_count(rgba.width, rgba.height) } else { 1 };
 
         let texture = device.create_texture(
             &wgpu::TextureDescriptor {
                 size: texture_size,
-                mip_level_count: 1,
+                mip_level_count,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
                 format: rgba.format,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
                 label: Some(label),
                 view_formats: &[],
             }
         );
 
-        queue.write_texture(
-            wgpu::TexelCopyTextureInfoBase {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            bytemuck::cast_slice(rgba.values.as_slice()),
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(std::mem::size_of::<T>() as u32 * rgba.width),
-                rows_per_image: Some(rgba.height),
-            },
-            texture_size,
+        assert_eq!(
+            rgba.values.len(),
+            rgba.width as usize * rgba.height as usize,
+            "Texture {} has {} pixels but is {}x{}", label, rgba.values.len(), rgba.width, rgba.height,
         );
 
+        // Premultiplied before downsampling, so transparent texels don't bleed their color into the smaller levels
+        let mut values = if premultiply_alpha {
+            std::borrow::Cow::Owned(rgba.values.iter().map(|p| p.premultiplied()).collect())
+        } else {
+            std::borrow::Cow::Borrowed(rgba.values.as_slice())
+        };
+
+        let (mut width, mut height) = (rgba.width, rgba.height);
+        for mip_level in 0..mip_level_count {
+            if mip_level > 0 {
+                values = std::borrow::Cow::Owned(downsample(&values, width, height));
+                (width, height) = ((width / 2).max(1), (height / 2).max(1));
+            }
+
+            // Upload with the same padded row layout a buffer copy of the texture would use, so the
+            // texture can be read back and compared row for row
+            let bytes_per_row = std::mem::size_of::<T>() as u32 * width;
+            let padded_bytes_per_row = padded_bytes_per_row(bytes_per_row);
+            let bytes = pad_rows(bytemuck::cast_slice(&values), bytes_per_row, padded_bytes_per_row);
+
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfoBase {
+                    texture: &texture,
+                    mip_level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &bytes,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
         let view = texture.create_view(&wgpu::TextureViewDescriptor {
             format: Some(rgba.format),
             ..Default::default()
         });
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::MirrorRepeat,
-            address_mode_v: wgpu::AddressMode::MirrorRepeat,
-            address_mode_w: wgpu::AddressMode::MirrorRepeat,
+            address_mode_u: sampler_config.address_mode_u,
+            address_mode_v: sampler_config.address_mode_v,
+            address_mode_w: sampler_config.address_mode_w,
+            mag_filter: sampler_config.mag_filter,
+            min_filter: sampler_config.min_filter,
+            mipmap_filter: sampler_config.mipmap_filter,
+            ..Default::default()
+        });
+
+        GpuTexture {
+            texture,
+            view,
+            sampler,
+        }
+
+    }
+
+    // Creates an empty texture to render into, which can then be sampled by a later pass
+    pub fn render_target(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                // Zero sized textures are invalid, the window can be zero sized while minimized
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: Some(label),
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
             mipmap_filter: wgpu::FilterMode::Nearest,
             ..Default::default()
         });
@@ -90,6 +410,138 @@ impl GpuTexture {
             view,
             sampler,
         }
+    }
+
+    // Creates a texture that's only rendered into and never sampled: a multisampled color target resolved into a
+    // regular texture, or a depth target. Only the view is needed, it keeps the texture alive
+    pub fn multisampled_target(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        label: &str,
+    ) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            label: Some(label),
+            view_formats: &[],
+        });
+        texture.create_view(&Default::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid_fills_every_pixel() {
+        let rgba = RgbaTexture::solid(3, 2, [10u8, 20, 30, 40], wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!((rgba.width, rgba.height, rgba.values.len()), (3, 2, 6));
+        for y in 0..2 {
+            for x in 0..3 {
+                assert_eq!(rgba.get_pixel(x, y), [10, 20, 30, 40]);
+            }
+        }
+    }
+
+    #[test]
+    fn unaligned_width_round_trips() {
+        let Some((device, queue)) = crate::tests::test_device() else { return };
+        // 400 bytes per row, which isn't a multiple of 256
+        let mut rgba = RgbaTexture::solid(100, 3, [0u8; 4], wgpu::TextureFormat::Rgba8Unorm);
+        for (i, pixel) in rgba.values.iter_mut().enumerate() {
+            *pixel = [i as u8, (i / 256) as u8, 7, 255];
+        }
+        let gpu_texture = GpuTexture::from_rgbatexture(&rgba, &device, &queue, "test", false, false, SamplerConfig::default());
+        let bytes = read_texture(&device, &queue, device.create_command_encoder(&Default::default()), &gpu_texture.texture);
+        assert_eq!(bytes, bytemuck::cast_slice::<[u8; 4], u8>(&rgba.values));
+    }
+
+    #[test]
+    fn png_round_trips() {
+        use image::ImageEncoder;
+        let pixels: Vec<[u8; 4]> = (0..6).map(|i| [i * 40, 255 - i * 40, 7, 128 + i]).collect();
+        let mut png = vec![];
+        image::codecs::png::PngEncoder::new(&mut png)
+            .write_image(bytemuck::cast_slice(&pixels), 3, 2, image::ExtendedColorType::Rgba8)
+            .unwrap();
+
+        let rgba = RgbaTexture::from_image_bytes(&png).unwrap();
+        assert_eq!((rgba.width, rgba.height), (3, 2));
+        assert_eq!(rgba.format, wgpu::TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(rgba.values, pixels);
+
+        assert!(RgbaTexture::from_image_bytes(&png[..png.len() / 2]).is_err());
+        assert!(RgbaTexture::from_image_bytes(b"not an image").is_err());
+    }
+
+    #[test]
+    fn mipmapped_texture_reports_every_level() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(8, 8), 4);
+        assert_eq!(mip_level_count(100, 3), 7);
+        assert_eq!(mip_level_count(512, 512), 10);
+
+        let Some((device, queue)) = crate::tests::test_device() else { return };
+        let rgba = RgbaTexture::solid(100, 3, [200u8, 100, 50, 255], wgpu::TextureFormat::Rgba8Unorm);
+        let mipmapped = GpuTexture::from_rgbatexture(&rgba, &device, &queue, "test", false, true, SamplerConfig::default());
+        assert_eq!(mipmapped.texture.mip_level_count(), 7);
+        let single = GpuTexture::from_rgbatexture(&rgba, &device, &queue, "test", false, false, SamplerConfig::default());
+        assert_eq!(single.texture.mip_level_count(), 1);
+    }
+
+    #[test]
+    fn checked_pixel_access() {
+        let mut rgba = RgbaTexture::solid(3, 2, [0u8; 4], wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!(rgba.try_set_pixel(2, 1, [1, 2, 3, 4]), Ok(()));
+        assert_eq!(rgba.try_get_pixel(2, 1), Ok([1, 2, 3, 4]));
+
+        // x = 3 would wrap onto the next row without the check
+        let error = PixelOutOfBounds { x: 3, y: 0, width: 3, height: 2 };
+        assert_eq!(rgba.try_get_pixel(3, 0), Err(error));
+        assert_eq!(rgba.try_set_pixel(3, 0, [9; 4]), Err(error));
+        assert_eq!(rgba.try_get_pixel(0, 2).unwrap_err().y, 2);
+        assert_eq!(rgba.values[3], [0; 4]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Pixel (3, 0) is outside of a 3x2 texture")]
+    fn out_of_range_pixel_panics_in_debug() {
+        RgbaTexture::solid(3, 2, [0u8; 4], wgpu::TextureFormat::Rgba8Unorm).get_pixel(3, 0);
+    }
+
+    #[test]
+    fn scaling_2x2_to_4x4_interpolates() {
+        let rgba = RgbaTexture {
+            values: vec![[0u8, 0, 0, 255], [200, 0, 0, 255], [0, 100, 0, 255], [200, 100, 0, 255]],
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            height: 2,
+            width: 2,
+        };
+        let scaled = rgba.scaled(4, 4);
+        assert_eq!((scaled.width, scaled.height, scaled.format), (4, 4, rgba.format));
+        // The corners keep the source colors, the pixels between them are a quarter and 3 quarters of the way
+        assert_eq!(scaled.get_pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(scaled.get_pixel(3, 3), [200, 100, 0, 255]);
+        assert_eq!(scaled.get_pixel(1, 0), [50, 0, 0, 255]);
+        assert_eq!(scaled.get_pixel(2, 0), [150, 0, 0, 255]);
+        assert_eq!(scaled.get_pixel(1, 1), [50, 25, 0, 255]);
+        assert_eq!(scaled.get_pixel(0, 2), [0, 75, 0, 255]);
 
+        // A texture with nothing to sample scales to an empty one instead of underflowing
+        let empty = RgbaTexture::solid(0, 3, [1u8; 4], wgpu::TextureFormat::Rgba8Unorm).scaled(4, 4);
+        assert_eq!((empty.width, empty.height, empty.values.len()), (0, 0, 0));
     }
 }
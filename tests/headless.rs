@@ -0,0 +1,26 @@
+// Drives the renderer through the library's public API only, the way another binary would embed it
+
+use std::sync::Arc;
+
+use wasm_wgpu::{AntiAliasing, InitContent, State};
+
+#[test]
+fn headless_state_renders_through_the_public_api() {
+    let init_content = InitContent { text: "hi".to_string(), anti_aliasing: AntiAliasing::None, ..InitContent::demo() };
+    let size = winit::dpi::PhysicalSize::new(64, 32);
+    let mut state = match pollster::block_on(State::new_headless(size, Arc::new(init_content))) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{e}, skipping the test");
+            return;
+        }
+    };
+
+    state.set_text("hello");
+    state.camera_mut().orbit(0.2, 0.1);
+    state.set_frustum_culling(true);
+    let image = state.render_to_image(64, 32);
+    assert_eq!((image.width, image.height), (64, 32));
+    assert!(image.values.iter().any(|pixel| pixel[3] != 0));
+    assert_eq!(state.config_snapshot().text, "hello");
+}